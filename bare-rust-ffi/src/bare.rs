@@ -0,0 +1,81 @@
+use std::ffi::c_void;
+use std::option::Option;
+use std::os::raw::{c_char, c_int};
+
+use crate::uv::{uv_buf_t, uv_loop_t, uv_run_mode};
+use crate::{js_env_t, js_platform_t, js_value_t};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct bare_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct bare_options_t {
+    pub version: c_int,
+    pub memory_limit: usize,
+}
+
+pub type bare_before_exit_cb = Option<unsafe extern "C" fn(bare: *mut bare_t, data: *mut c_void)>;
+
+pub type bare_exit_cb = Option<unsafe extern "C" fn(bare: *mut bare_t, data: *mut c_void)>;
+
+pub type bare_suspend_cb =
+    Option<unsafe extern "C" fn(bare: *mut bare_t, linger: c_int, data: *mut c_void)>;
+
+pub type bare_idle_cb = Option<unsafe extern "C" fn(bare: *mut bare_t, data: *mut c_void)>;
+
+pub type bare_resume_cb = Option<unsafe extern "C" fn(bare: *mut bare_t, data: *mut c_void)>;
+
+unsafe extern "C" {
+    pub fn bare_setup(
+        loop_: *mut uv_loop_t,
+        platform: *mut js_platform_t,
+        env: *mut *mut js_env_t,
+        argc: c_int,
+        argv: *const *const c_char,
+        options: *const bare_options_t,
+        result: *mut *mut bare_t,
+    ) -> c_int;
+
+    pub fn bare_teardown(bare: *mut bare_t, mode: uv_run_mode, exit_code: *mut c_int) -> c_int;
+
+    pub fn bare_exit(bare: *mut bare_t, exit_code: c_int) -> c_int;
+
+    pub fn bare_terminate(bare: *mut bare_t) -> c_int;
+
+    pub fn bare_load(
+        bare: *mut bare_t,
+        filename: *const c_char,
+        source: *const uv_buf_t,
+        result: *mut *mut js_value_t,
+    ) -> c_int;
+
+    pub fn bare_run(bare: *mut bare_t, mode: uv_run_mode) -> c_int;
+
+    pub fn bare_suspend(bare: *mut bare_t, linger: c_int) -> c_int;
+
+    pub fn bare_wakeup(bare: *mut bare_t, deadline: c_int) -> c_int;
+
+    pub fn bare_resume(bare: *mut bare_t) -> c_int;
+
+    pub fn bare_get_data(bare: *mut bare_t, result: *mut *mut c_void) -> c_int;
+
+    pub fn bare_set_data(bare: *mut bare_t, data: *mut c_void) -> c_int;
+
+    pub fn bare_on_before_exit(
+        bare: *mut bare_t,
+        cb: bare_before_exit_cb,
+        data: *mut c_void,
+    ) -> c_int;
+
+    pub fn bare_on_exit(bare: *mut bare_t, cb: bare_exit_cb, data: *mut c_void) -> c_int;
+
+    pub fn bare_on_suspend(bare: *mut bare_t, cb: bare_suspend_cb, data: *mut c_void) -> c_int;
+
+    pub fn bare_on_idle(bare: *mut bare_t, cb: bare_idle_cb, data: *mut c_void) -> c_int;
+
+    pub fn bare_on_resume(bare: *mut bare_t, cb: bare_resume_cb, data: *mut c_void) -> c_int;
+}
@@ -4,6 +4,23 @@ use std::ffi::c_void;
 use std::option::Option;
 use std::os::raw::{c_char, c_double, c_int, c_uchar};
 
+pub mod bare;
+pub mod uv;
+
+use uv::uv_loop_t;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct js_platform_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct js_platform_options_t {
+    _private: (),
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct js_env_t {
@@ -58,6 +75,12 @@ pub struct js_arraybuffer_backing_store_t {
     _private: (),
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct js_threadsafe_function_t {
+    _private: (),
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct js_property_descriptor_t {
@@ -89,6 +112,12 @@ pub struct js_type_tag_t {
     pub upper: u64,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct js_typed_callback_info_t {
+    _private: (),
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct js_callback_signature_t {
@@ -105,6 +134,8 @@ pub type js_function_cb = Option<
 pub type js_finalize_cb =
     Option<unsafe extern "C" fn(env: *mut js_env_t, data: *mut c_void, finalize_hint: *mut c_void)>;
 
+pub type js_teardown_cb = Option<unsafe extern "C" fn(data: *mut c_void)>;
+
 pub type js_delegate_get_cb = Option<
     unsafe extern "C" fn(
         env: *mut js_env_t,
@@ -133,6 +164,15 @@ pub type js_delegate_delete_property_cb = Option<
 pub type js_delegate_own_keys_cb =
     Option<unsafe extern "C" fn(arg1: *mut js_env_t, data: *mut c_void) -> *mut js_value_t>;
 
+pub type js_threadsafe_function_cb = Option<
+    unsafe extern "C" fn(
+        env: *mut js_env_t,
+        function: *mut js_value_t,
+        context: *mut c_void,
+        data: *mut c_void,
+    ),
+>;
+
 pub const JS_PENDING_EXCEPTION: c_int = -1;
 pub const JS_UNCAUGHT_EXCEPTION: c_int = -2;
 
@@ -149,6 +189,20 @@ pub enum js_value_type_t {
     js_function = 7,
     js_external = 8,
     js_bigint = 9,
+    // Extended types for the result and arguments of `js_callback_signature_t`.
+    js_int8 = 10,
+    js_uint8 = 11,
+    js_int16 = 12,
+    js_uint16 = 13,
+    js_int32 = 14,
+    js_uint32 = 15,
+    js_int64 = 16,
+    js_uint64 = 17,
+    js_float16 = 18,
+    js_float32 = 19,
+    js_float64 = 20,
+    js_bigint64 = 21,
+    js_biguint64 = 22,
 }
 
 #[repr(C)]
@@ -208,7 +262,29 @@ pub enum js_index_filter_t {
     js_index_skip_indices = 1,
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub enum js_threadsafe_function_call_mode_t {
+    js_threadsafe_function_nonblocking = 0,
+    js_threadsafe_function_blocking = 1,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub enum js_threadsafe_function_release_mode_t {
+    js_threadsafe_function_release = 0,
+    js_threadsafe_function_abort = 1,
+}
+
 unsafe extern "C" {
+    pub fn js_create_platform(
+        loop_: *mut uv_loop_t,
+        options: *const js_platform_options_t,
+        result: *mut *mut js_platform_t,
+    ) -> c_int;
+
+    pub fn js_destroy_platform(platform: *mut js_platform_t) -> c_int;
+
     pub fn js_open_handle_scope(env: *mut js_env_t, result: *mut *mut js_handle_scope_t) -> c_int;
 
     pub fn js_close_handle_scope(env: *mut js_env_t, scope: *mut js_handle_scope_t) -> c_int;
@@ -240,6 +316,20 @@ unsafe extern "C" {
 
     pub fn js_get_bindings(env: *mut js_env_t, result: *mut *mut js_value_t) -> c_int;
 
+    pub fn js_get_env_loop(env: *mut js_env_t, result: *mut *mut uv_loop_t) -> c_int;
+
+    pub fn js_add_teardown_callback(
+        env: *mut js_env_t,
+        callback: js_teardown_cb,
+        data: *mut c_void,
+    ) -> c_int;
+
+    pub fn js_remove_teardown_callback(
+        env: *mut js_env_t,
+        callback: js_teardown_cb,
+        data: *mut c_void,
+    ) -> c_int;
+
     pub fn js_run_script(
         env: *mut js_env_t,
         file: *const c_char,
@@ -370,6 +460,14 @@ unsafe extern "C" {
         result: *mut *mut js_value_t,
     ) -> c_int;
 
+    pub fn js_create_bigint_words(
+        env: *mut js_env_t,
+        sign: c_int,
+        words: *const u64,
+        len: usize,
+        result: *mut *mut js_value_t,
+    ) -> c_int;
+
     pub fn js_create_string_utf8(
         env: *mut js_env_t,
         string: *const c_uchar,
@@ -377,6 +475,20 @@ unsafe extern "C" {
         result: *mut *mut js_value_t,
     ) -> c_int;
 
+    pub fn js_create_string_utf16le(
+        env: *mut js_env_t,
+        string: *const u16,
+        len: usize,
+        result: *mut *mut js_value_t,
+    ) -> c_int;
+
+    pub fn js_create_string_latin1(
+        env: *mut js_env_t,
+        string: *const c_uchar,
+        len: usize,
+        result: *mut *mut js_value_t,
+    ) -> c_int;
+
     pub fn js_create_external_string_utf8(
         env: *mut js_env_t,
         string: *mut c_uchar,
@@ -387,6 +499,26 @@ unsafe extern "C" {
         copied: *mut bool,
     ) -> c_int;
 
+    pub fn js_create_external_string_utf16le(
+        env: *mut js_env_t,
+        string: *mut u16,
+        len: usize,
+        finalize_cb: js_finalize_cb,
+        finalize_hint: *mut c_void,
+        result: *mut *mut js_value_t,
+        copied: *mut bool,
+    ) -> c_int;
+
+    pub fn js_create_external_string_latin1(
+        env: *mut js_env_t,
+        string: *mut c_uchar,
+        len: usize,
+        finalize_cb: js_finalize_cb,
+        finalize_hint: *mut c_void,
+        result: *mut *mut js_value_t,
+        copied: *mut bool,
+    ) -> c_int;
+
     pub fn js_create_property_key_utf8(
         env: *mut js_env_t,
         string: *const c_uchar,
@@ -883,6 +1015,15 @@ unsafe extern "C" {
         lossless: *mut bool,
     ) -> c_int;
 
+    pub fn js_get_value_bigint_words(
+        env: *mut js_env_t,
+        value: *mut js_value_t,
+        sign: *mut c_int,
+        words: *mut u64,
+        len: usize,
+        result: *mut usize,
+    ) -> c_int;
+
     pub fn js_get_value_string_utf8(
         env: *mut js_env_t,
         value: *mut js_value_t,
@@ -891,6 +1032,22 @@ unsafe extern "C" {
         result: *mut usize,
     ) -> c_int;
 
+    pub fn js_get_value_string_utf16le(
+        env: *mut js_env_t,
+        value: *mut js_value_t,
+        string: *mut u16,
+        len: usize,
+        result: *mut usize,
+    ) -> c_int;
+
+    pub fn js_get_value_string_latin1(
+        env: *mut js_env_t,
+        value: *mut js_value_t,
+        string: *mut c_uchar,
+        len: usize,
+        result: *mut usize,
+    ) -> c_int;
+
     pub fn js_get_value_external(
         env: *mut js_env_t,
         value: *mut js_value_t,
@@ -1048,6 +1205,12 @@ unsafe extern "C" {
         data: *mut *mut c_void,
     ) -> c_int;
 
+    pub fn js_get_typed_callback_info(
+        info: *const js_typed_callback_info_t,
+        env: *mut *mut js_env_t,
+        data: *mut *mut c_void,
+    ) -> c_int;
+
     pub fn js_get_new_target(
         env: *mut js_env_t,
         info: *const js_callback_info_t,
@@ -1113,6 +1276,46 @@ unsafe extern "C" {
         result: *mut *mut js_value_t,
     ) -> c_int;
 
+    pub fn js_create_threadsafe_function(
+        env: *mut js_env_t,
+        function: *mut js_value_t,
+        queue_limit: usize,
+        initial_thread_count: usize,
+        finalize_cb: js_finalize_cb,
+        finalize_hint: *mut c_void,
+        context: *mut c_void,
+        cb: js_threadsafe_function_cb,
+        result: *mut *mut js_threadsafe_function_t,
+    ) -> c_int;
+
+    pub fn js_get_threadsafe_function_context(
+        function: *mut js_threadsafe_function_t,
+        result: *mut *mut c_void,
+    ) -> c_int;
+
+    pub fn js_call_threadsafe_function(
+        function: *mut js_threadsafe_function_t,
+        data: *mut c_void,
+        mode: js_threadsafe_function_call_mode_t,
+    ) -> c_int;
+
+    pub fn js_acquire_threadsafe_function(function: *mut js_threadsafe_function_t) -> c_int;
+
+    pub fn js_release_threadsafe_function(
+        function: *mut js_threadsafe_function_t,
+        mode: js_threadsafe_function_release_mode_t,
+    ) -> c_int;
+
+    pub fn js_ref_threadsafe_function(
+        env: *mut js_env_t,
+        function: *mut js_threadsafe_function_t,
+    ) -> c_int;
+
+    pub fn js_unref_threadsafe_function(
+        env: *mut js_env_t,
+        function: *mut js_threadsafe_function_t,
+    ) -> c_int;
+
     pub fn js_throw(env: *mut js_env_t, error: *mut js_value_t) -> c_int;
 
     pub fn js_is_exception_pending(env: *mut js_env_t, result: *mut bool) -> c_int;
@@ -1121,4 +1324,6 @@ unsafe extern "C" {
         env: *mut js_env_t,
         result: *mut *mut js_value_t,
     ) -> c_int;
+
+    pub fn js_fatal_exception(env: *mut js_env_t, error: *mut js_value_t) -> c_int;
 }
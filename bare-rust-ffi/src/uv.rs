@@ -0,0 +1,246 @@
+use std::ffi::c_void;
+use std::option::Option;
+use std::os::raw::{c_char, c_int, c_uint};
+
+#[cfg(windows)]
+use std::os::raw::c_ulong;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_loop_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_handle_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_async_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_timer_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_poll_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_req_t {
+    _private: (),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_work_t {
+    _private: (),
+}
+
+#[cfg(not(windows))]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_buf_t {
+    pub base: *mut c_char,
+    pub len: usize,
+}
+
+#[cfg(windows)]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct uv_buf_t {
+    pub len: c_ulong,
+    pub base: *mut c_char,
+}
+
+#[cfg(not(windows))]
+pub type uv_os_sock_t = c_int;
+
+#[cfg(windows)]
+pub type uv_os_sock_t = usize;
+
+pub type uv_close_cb = Option<unsafe extern "C" fn(handle: *mut uv_handle_t)>;
+
+pub type uv_async_cb = Option<unsafe extern "C" fn(handle: *mut uv_async_t)>;
+
+pub type uv_timer_cb = Option<unsafe extern "C" fn(handle: *mut uv_timer_t)>;
+
+pub type uv_poll_cb =
+    Option<unsafe extern "C" fn(handle: *mut uv_poll_t, status: c_int, events: c_int)>;
+
+pub type uv_work_cb = Option<unsafe extern "C" fn(req: *mut uv_work_t)>;
+
+pub type uv_after_work_cb = Option<unsafe extern "C" fn(req: *mut uv_work_t, status: c_int)>;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub enum uv_handle_type {
+    UV_UNKNOWN_HANDLE = 0,
+    UV_ASYNC = 1,
+    UV_CHECK = 2,
+    UV_FS_EVENT = 3,
+    UV_FS_POLL = 4,
+    UV_HANDLE = 5,
+    UV_IDLE = 6,
+    UV_NAMED_PIPE = 7,
+    UV_POLL = 8,
+    UV_PREPARE = 9,
+    UV_PROCESS = 10,
+    UV_STREAM = 11,
+    UV_TCP = 12,
+    UV_TIMER = 13,
+    UV_TTY = 14,
+    UV_UDP = 15,
+    UV_SIGNAL = 16,
+    UV_FILE = 17,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub enum uv_req_type {
+    UV_UNKNOWN_REQ = 0,
+    UV_REQ = 1,
+    UV_CONNECT = 2,
+    UV_WRITE = 3,
+    UV_SHUTDOWN = 4,
+    UV_UDP_SEND = 5,
+    UV_FS = 6,
+    UV_WORK = 7,
+    UV_GETADDRINFO = 8,
+    UV_GETNAMEINFO = 9,
+    UV_RANDOM = 10,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub enum uv_run_mode {
+    UV_RUN_DEFAULT = 0,
+    UV_RUN_ONCE = 1,
+    UV_RUN_NOWAIT = 2,
+}
+
+pub const UV_READABLE: c_int = 1;
+pub const UV_WRITABLE: c_int = 2;
+pub const UV_DISCONNECT: c_int = 4;
+pub const UV_PRIORITIZED: c_int = 8;
+
+#[cfg(not(windows))]
+pub const UV_EINVAL: c_int = -22;
+#[cfg(windows)]
+pub const UV_EINVAL: c_int = -4071;
+
+unsafe extern "C" {
+    pub fn uv_default_loop() -> *mut uv_loop_t;
+
+    pub fn uv_loop_init(loop_: *mut uv_loop_t) -> c_int;
+
+    pub fn uv_loop_close(loop_: *mut uv_loop_t) -> c_int;
+
+    pub fn uv_loop_size() -> usize;
+
+    pub fn uv_loop_alive(loop_: *const uv_loop_t) -> c_int;
+
+    pub fn uv_run(loop_: *mut uv_loop_t, mode: uv_run_mode) -> c_int;
+
+    pub fn uv_stop(loop_: *mut uv_loop_t);
+
+    pub fn uv_now(loop_: *const uv_loop_t) -> u64;
+
+    pub fn uv_update_time(loop_: *mut uv_loop_t);
+
+    pub fn uv_strerror(err: c_int) -> *const c_char;
+
+    pub fn uv_err_name(err: c_int) -> *const c_char;
+
+    pub fn uv_handle_size(type_: uv_handle_type) -> usize;
+
+    pub fn uv_handle_get_type(handle: *const uv_handle_t) -> uv_handle_type;
+
+    pub fn uv_handle_get_loop(handle: *const uv_handle_t) -> *mut uv_loop_t;
+
+    pub fn uv_handle_get_data(handle: *const uv_handle_t) -> *mut c_void;
+
+    pub fn uv_handle_set_data(handle: *mut uv_handle_t, data: *mut c_void);
+
+    pub fn uv_is_active(handle: *const uv_handle_t) -> c_int;
+
+    pub fn uv_is_closing(handle: *const uv_handle_t) -> c_int;
+
+    pub fn uv_close(handle: *mut uv_handle_t, close_cb: uv_close_cb);
+
+    pub fn uv_ref(handle: *mut uv_handle_t);
+
+    pub fn uv_unref(handle: *mut uv_handle_t);
+
+    pub fn uv_has_ref(handle: *const uv_handle_t) -> c_int;
+
+    pub fn uv_req_size(type_: uv_req_type) -> usize;
+
+    pub fn uv_req_get_data(req: *const uv_req_t) -> *mut c_void;
+
+    pub fn uv_req_set_data(req: *mut uv_req_t, data: *mut c_void);
+
+    pub fn uv_cancel(req: *mut uv_req_t) -> c_int;
+
+    pub fn uv_async_init(
+        loop_: *mut uv_loop_t,
+        handle: *mut uv_async_t,
+        async_cb: uv_async_cb,
+    ) -> c_int;
+
+    pub fn uv_async_send(handle: *mut uv_async_t) -> c_int;
+
+    pub fn uv_timer_init(loop_: *mut uv_loop_t, handle: *mut uv_timer_t) -> c_int;
+
+    pub fn uv_timer_start(
+        handle: *mut uv_timer_t,
+        cb: uv_timer_cb,
+        timeout: u64,
+        repeat: u64,
+    ) -> c_int;
+
+    pub fn uv_timer_stop(handle: *mut uv_timer_t) -> c_int;
+
+    pub fn uv_timer_again(handle: *mut uv_timer_t) -> c_int;
+
+    pub fn uv_timer_set_repeat(handle: *mut uv_timer_t, repeat: u64);
+
+    pub fn uv_timer_get_repeat(handle: *const uv_timer_t) -> u64;
+
+    pub fn uv_timer_get_due_in(handle: *const uv_timer_t) -> u64;
+
+    pub fn uv_poll_init(loop_: *mut uv_loop_t, handle: *mut uv_poll_t, fd: c_int) -> c_int;
+
+    pub fn uv_poll_init_socket(
+        loop_: *mut uv_loop_t,
+        handle: *mut uv_poll_t,
+        socket: uv_os_sock_t,
+    ) -> c_int;
+
+    pub fn uv_poll_start(handle: *mut uv_poll_t, events: c_int, cb: uv_poll_cb) -> c_int;
+
+    pub fn uv_poll_stop(handle: *mut uv_poll_t) -> c_int;
+
+    pub fn uv_queue_work(
+        loop_: *mut uv_loop_t,
+        req: *mut uv_work_t,
+        work_cb: uv_work_cb,
+        after_work_cb: uv_after_work_cb,
+    ) -> c_int;
+
+    pub fn uv_buf_init(base: *mut c_char, len: c_uint) -> uv_buf_t;
+
+    pub fn uv_version() -> c_uint;
+
+    pub fn uv_version_string() -> *const c_char;
+}
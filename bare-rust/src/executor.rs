@@ -0,0 +1,134 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::mem::ManuallyDrop;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::ffi::*;
+use crate::{registry, uv, Env, Error, Result};
+
+type Output = thread::Result<Box<dyn Any + Send>>;
+
+type Completion = Box<dyn for<'a> FnOnce(&mut Env<'a>, Output) -> Result<()>>;
+
+// The tokio runtime of an environment. Futures run on the runtime threads and
+// send their output back to the JavaScript thread, where the async handle wakes
+// the loop to run their completions.
+struct Executor {
+    runtime: ManuallyDrop<Runtime>,
+    signal: uv::Async,
+    sender: mpsc::Sender<(u64, Output)>,
+    receiver: mpsc::Receiver<(u64, Output)>,
+    pending: RefCell<HashMap<u64, Completion>>,
+    next_id: Cell<u64>,
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // Dropping the runtime would block the JavaScript thread until the
+        // runtime threads have finished.
+        unsafe { ManuallyDrop::take(&mut self.runtime) }.shutdown_background();
+    }
+}
+
+fn executor(env: &Env) -> Result<Rc<Executor>> {
+    registry::state_or_insert_with(env, || {
+        let runtime = match Builder::new_multi_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(err) => return Err(env.throw(Error::new(env, &err.to_string())?)),
+        };
+
+        let (sender, receiver) = mpsc::channel();
+
+        Ok(Executor {
+            runtime: ManuallyDrop::new(runtime),
+            signal: registry::signal(env, drain)?,
+            sender,
+            receiver,
+            pending: RefCell::new(HashMap::new()),
+            next_id: Cell::new(0),
+        })
+    })
+}
+
+pub(crate) fn handle(env: &Env) -> Result<Handle> {
+    Ok(executor(env)?.runtime.handle().clone())
+}
+
+// Spawns `future` on the runtime of the environment and passes its output, or
+// the payload of its panic, to `complete` on the JavaScript thread.
+pub(crate) fn spawn<F, C>(env: &Env, future: F, complete: C) -> Result<()>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+    C: for<'a> FnOnce(&mut Env<'a>, thread::Result<F::Output>) -> Result<()> + 'static,
+{
+    let executor = executor(env)?;
+
+    let id = executor.next_id.get();
+
+    executor.next_id.set(id + 1);
+
+    let completion: Completion = Box::new(move |env, output| {
+        complete(
+            env,
+            output.map(|output| match output.downcast::<F::Output>() {
+                Ok(output) => *output,
+                Err(_) => unreachable!(),
+            }),
+        )
+    });
+
+    executor.pending.borrow_mut().insert(id, completion);
+
+    executor.signal.reference();
+
+    let sender = executor.sender.clone();
+    let signal = executor.signal.sender();
+
+    let task = executor.runtime.spawn(future);
+
+    executor.runtime.spawn(async move {
+        let output: Output = match task.await {
+            Ok(output) => Ok(Box::new(output)),
+            Err(err) if err.is_panic() => Err(err.into_panic()),
+            Err(_) => return,
+        };
+
+        if sender.send((id, output)).is_ok() {
+            signal.send();
+        }
+    });
+
+    Ok(())
+}
+
+fn drain(env: *mut js_env_t) {
+    let Some(executor) = registry::state::<Executor>(&Env::from_raw(env)) else {
+        return;
+    };
+
+    let completions: Vec<_> = {
+        let mut pending = executor.pending.borrow_mut();
+
+        let completions = executor
+            .receiver
+            .try_iter()
+            .filter_map(|(id, output)| pending.remove(&id).map(|complete| (complete, output)))
+            .collect();
+
+        if pending.is_empty() {
+            executor.signal.unreference();
+        }
+
+        completions
+    };
+
+    for (complete, output) in completions {
+        Env::run_callback(env, |env| complete(env, output));
+    }
+}
@@ -1,1002 +1,3270 @@
-use std::ffi::{c_void, CString};
+use std::any::Any;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::error;
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::process;
 use std::ptr;
+use std::rc::Rc;
 use std::result;
 use std::slice;
+use std::str;
 use std::string;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::task::{self, Waker};
+use std::thread;
 
 pub use bare_rust_ffi as ffi;
 
 use ffi::*;
 
-macro_rules! assert_status {
-    ($status:expr, $message:expr) => {
-        assert!($status == 0, $message);
-    };
-}
-
 macro_rules! check_status {
-    ($env:expr, $status:expr) => {
-        if $status == JS_PENDING_EXCEPTION {
-            return Err($env.pending_exception());
-        } else {
-            assert_status!($status, "Uncaught JavaScript exception");
+    ($status:expr) => {
+        if $status != 0 {
+            return Err(JsException::from($status));
         }
     };
 }
 
-type Result<T> = result::Result<T, Value>;
-
-#[derive(Debug)]
-pub struct Env {
-    ptr: *mut js_env_t,
-}
+pub mod uv;
 
-impl Env {
-    pub fn is_exception_pending(&self) -> bool {
-        let mut result = false;
+#[cfg(feature = "tokio")]
+mod executor;
 
-        unsafe {
-            js_is_exception_pending(self.ptr, &mut result);
-        }
+mod local;
+mod registry;
+mod runtime;
 
-        result
-    }
+pub use runtime::Runtime;
 
-    pub fn pending_exception(&self) -> Value {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsException {
+    Pending,
+    Uncaught,
+    TornDown,
+    Status(i32),
+}
 
-        unsafe {
-            js_get_and_clear_last_exception(self.ptr, &mut ptr);
+impl From<c_int> for JsException {
+    fn from(status: c_int) -> Self {
+        match status {
+            JS_PENDING_EXCEPTION => JsException::Pending,
+            JS_UNCAUGHT_EXCEPTION => JsException::Uncaught,
+            status => JsException::Status(status),
         }
-
-        Value { env: self.ptr, ptr }
     }
 }
 
-impl From<*mut js_env_t> for Env {
-    fn from(ptr: *mut js_env_t) -> Self {
-        Self { ptr }
+impl fmt::Display for JsException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsException::Pending => write!(f, "Pending JavaScript exception"),
+            JsException::Uncaught => write!(f, "Uncaught JavaScript exception"),
+            JsException::TornDown => write!(f, "JavaScript environment has been torn down"),
+            JsException::Status(status) => write!(f, "JavaScript engine error ({})", status),
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct Scope {
-    env: *mut js_env_t,
-    ptr: *mut js_handle_scope_t,
-}
-
-impl Scope {
-    pub fn new(env: &Env) -> Self {
-        let mut ptr: *mut js_handle_scope_t = ptr::null_mut();
+impl error::Error for JsException {}
 
-        unsafe {
-            js_open_handle_scope(env.ptr, &mut ptr);
-        }
+pub type Result<T> = result::Result<T, JsException>;
 
-        Self { env: env.ptr, ptr }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicPolicy {
+    Abort,
+    Throw,
 }
 
-impl Drop for Scope {
-    fn drop(&mut self) {
-        unsafe {
-            js_close_handle_scope(self.env, self.ptr);
-        }
+static PANIC_POLICY: AtomicU8 = AtomicU8::new(PanicPolicy::Throw as u8);
+
+pub fn panic_policy() -> PanicPolicy {
+    match PANIC_POLICY.load(Ordering::Relaxed) {
+        0 => PanicPolicy::Abort,
+        _ => PanicPolicy::Throw,
     }
 }
 
-#[derive(Debug)]
-pub struct EscapableScope {
-    env: *mut js_env_t,
-    ptr: *mut js_escapable_handle_scope_t,
+pub fn set_panic_policy(policy: PanicPolicy) {
+    PANIC_POLICY.store(policy as u8, Ordering::Relaxed);
 }
 
-impl EscapableScope {
-    pub fn new(env: &Env) -> Self {
-        let mut ptr: *mut js_escapable_handle_scope_t = ptr::null_mut();
-
-        unsafe {
-            js_open_escapable_handle_scope(env.ptr, &mut ptr);
-        }
+/// Runs a callback invoked from JavaScript, converting returned errors that are
+/// not already pending and Rust panics into thrown JavaScript exceptions so that
+/// neither unwinds across the C frames of the engine.
+#[doc(hidden)]
+pub unsafe fn catch_panic<F>(env: *mut js_env_t, f: F) -> *mut js_value_t
+where
+    F: for<'s> FnOnce(&mut Env<'s>) -> Result<Value<'s>>,
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let _root = registry::Root::new(env);
+
+        f(&mut Env::from_raw(env)).map(|result| result.ptr)
+    }));
+
+    settle(env, result)
+}
 
-        Self { env: env.ptr, ptr }
-    }
+fn settle(env: *mut js_env_t, result: thread::Result<Result<*mut js_value_t>>) -> *mut js_value_t {
+    let env = &Env::from_raw(env);
 
-    pub fn escape<T>(self, escapee: T) -> Value
-    where
-        T: Into<Value>,
-    {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+    match result {
+        Ok(Ok(result)) => result,
+        Ok(Err(JsException::Pending)) => ptr::null_mut(),
+        Ok(Err(err)) => {
+            if !matches!(env.is_exception_pending(), Ok(true)) {
+                throw_message(env, &err.to_string());
+            }
 
-        unsafe {
-            js_escape_handle(self.env, self.ptr, escapee.into().ptr, &mut ptr);
+            ptr::null_mut()
         }
+        Err(payload) => {
+            if panic_policy() == PanicPolicy::Abort {
+                process::abort();
+            }
 
-        Value { env: self.env, ptr }
-    }
-}
+            throw_message(env, panic_message(payload.as_ref()));
 
-impl Drop for EscapableScope {
-    fn drop(&mut self) {
-        unsafe {
-            js_close_escapable_handle_scope(self.env, self.ptr);
+            ptr::null_mut()
         }
     }
 }
 
-#[derive(Debug)]
-pub struct Ref {
-    env: *mut js_env_t,
-    ptr: *mut js_ref_t,
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<string::String>() {
+        message.as_str()
+    } else {
+        "Rust panic"
+    }
 }
 
-impl Ref {
-    pub fn new<T>(env: &Env, value: T) -> Self
-    where
-        T: Into<Value>,
-    {
-        let mut ptr: *mut js_ref_t = ptr::null_mut();
+fn throw_message(env: &Env, message: &str) {
+    let mut string: *mut js_value_t = ptr::null_mut();
+    let mut error: *mut js_value_t = ptr::null_mut();
 
-        unsafe {
-            js_create_reference(env.ptr, value.into().ptr, 1, &mut ptr);
+    unsafe {
+        if js_create_string_utf8(env.ptr, message.as_ptr(), message.len(), &mut string) != 0 {
+            return;
         }
 
-        Ref { env: env.ptr, ptr }
-    }
-
-    pub fn get<T>(&self) -> T
-    where
-        T: From<Value>,
-    {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
-
-        unsafe {
-            js_get_reference_value(self.env, self.ptr, &mut ptr);
+        if js_create_error(env.ptr, ptr::null_mut(), string, &mut error) != 0 {
+            return;
         }
 
-        Value { env: self.env, ptr }.into()
+        js_throw(env.ptr, error);
     }
 }
 
-impl Drop for Ref {
-    fn drop(&mut self) {
-        unsafe {
-            js_delete_reference(self.env, self.ptr);
-        }
-    }
+// Strings passed to the engine or Bare as C strings cannot contain interior NUL
+// bytes.
+pub(crate) fn c_string(string: &str) -> Result<CString> {
+    CString::new(string).map_err(|_| JsException::Status(ffi::uv::UV_EINVAL))
 }
 
-impl From<Ref> for Value {
-    fn from(reference: Ref) -> Self {
-        reference.get()
-    }
-}
+type TeardownClosure = Box<dyn FnOnce()>;
 
 #[derive(Debug)]
-pub struct WeakRef {
-    env: *mut js_env_t,
-    ptr: *mut js_ref_t,
+pub struct Env<'s> {
+    ptr: *mut js_env_t,
+    scope: PhantomData<&'s ()>,
 }
 
-impl WeakRef {
-    pub fn new<T>(env: &Env, value: T) -> Self
-    where
-        T: Into<Value>,
-    {
-        let mut ptr: *mut js_ref_t = ptr::null_mut();
-
-        unsafe {
-            js_create_reference(env.ptr, value.into().ptr, 0, &mut ptr);
+impl<'s> Env<'s> {
+    fn from_raw(ptr: *mut js_env_t) -> Self {
+        Self {
+            ptr,
+            scope: PhantomData,
         }
+    }
 
-        WeakRef { env: env.ptr, ptr }
+    pub fn is_exception_pending(&self) -> Result<bool> {
+        let mut result = false;
+
+        let status = unsafe { js_is_exception_pending(self.ptr, &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
     }
 
-    pub fn get<T>(&self) -> Option<T>
-    where
-        T: From<Value>,
-    {
+    pub fn pending_exception(&self) -> Result<Value<'s>> {
         let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        unsafe {
-            js_get_reference_value(self.env, self.ptr, &mut ptr);
-        }
+        let status = unsafe { js_get_and_clear_last_exception(self.ptr, &mut ptr) };
 
-        if ptr.is_null() {
-            None
-        } else {
-            Some(Value { env: self.env, ptr }.into())
-        }
+        check_status!(status);
+
+        Ok(Value::from_raw(self.ptr, ptr))
     }
-}
 
-impl Drop for WeakRef {
-    fn drop(&mut self) {
-        unsafe {
-            js_delete_reference(self.env, self.ptr);
+    pub fn throw<'a, T>(&self, error: T) -> JsException
+    where
+        T: Into<Value<'a>>,
+    {
+        match unsafe { js_throw(self.ptr, error.into().ptr) } {
+            0 => JsException::Pending,
+            status => JsException::from(status),
         }
     }
-}
 
-#[derive(Debug)]
-pub struct Value {
-    env: *mut js_env_t,
-    ptr: *mut js_value_t,
-}
+    pub fn global(&self) -> Result<Object<'s>> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
 
-impl From<Value> for *mut js_value_t {
-    fn from(value: Value) -> Self {
-        value.ptr
+        let status = unsafe { js_get_global(self.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Object(Value::from_raw(self.ptr, ptr)))
     }
-}
 
-macro_rules! value_conversions {
-    ($type: ident) => {
-        impl From<$type> for *mut js_value_t {
-            fn from(value: $type) -> Self {
-                value.0.ptr
-            }
-        }
+    pub fn bindings(&self) -> Result<Object<'s>> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        impl From<$type> for Value {
-            fn from(value: $type) -> Self {
-                value.0
-            }
-        }
+        let status = unsafe { js_get_bindings(self.ptr, &mut ptr) };
 
-        impl From<Value> for $type {
-            fn from(value: Value) -> Self {
-                Self(value)
-            }
-        }
-    };
-}
+        check_status!(status);
 
-#[derive(Debug)]
-pub struct Undefined(Value);
+        Ok(Object(Value::from_raw(self.ptr, ptr)))
+    }
+
+    /// Evaluates `source` as a classic script and returns its completion value.
+    /// `line_offset` is added to line numbers reported in stack traces.
+    pub fn run_script(&self, filename: &str, source: &str, line_offset: i32) -> Result<Value<'s>> {
+        let source = String::new(self, source)?;
 
-impl Undefined {
-    pub fn new(env: &Env) -> Self {
         let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        unsafe {
-            js_get_undefined(env.ptr, &mut ptr);
-        }
+        let status = unsafe {
+            js_run_script(
+                self.ptr,
+                filename.as_ptr() as *const c_char,
+                filename.len(),
+                line_offset,
+                source.0.ptr,
+                &mut ptr,
+            )
+        };
 
-        Self(Value { env: env.ptr, ptr })
+        check_status!(status);
+
+        Ok(Value::from_raw(self.ptr, ptr))
     }
-}
 
-value_conversions!(Undefined);
+    /// Runs `work` on the libuv thread pool and passes its result to `complete`
+    /// back on the JavaScript thread. A panic in `work` is handled according to
+    /// the panic policy once it reaches the JavaScript thread, and exceptions
+    /// left pending by `complete` are reported as uncaught. `complete` is not
+    /// run if the environment is torn down before `work` finishes.
+    pub fn spawn_blocking<W, R, C>(&self, work: W, complete: C) -> Result<()>
+    where
+        W: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+        C: for<'a> FnOnce(&mut Env<'a>, R) -> Result<()> + 'static,
+    {
+        let env = self.ptr;
 
-#[derive(Debug)]
-pub struct Null(Value);
+        let alive = registry::liveness(self)?;
 
-impl Null {
-    pub fn new(env: &Env) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+        let work = move || panic::catch_unwind(AssertUnwindSafe(work));
 
-        unsafe {
-            js_get_null(env.ptr, &mut ptr);
-        }
+        uv::queue_work(&self.uv_loop()?, work, move |result| {
+            if let Ok(result) = result
+                && alive.load(Ordering::Acquire)
+            {
+                Env::run_callback(env, |env| match result {
+                    Ok(result) => complete(env, result),
+                    Err(payload) => panic::resume_unwind(payload),
+                });
+            }
+        })?;
 
-        Self(Value { env: env.ptr, ptr })
+        Ok(())
     }
-}
 
-value_conversions!(Null);
+    /// Runs `f` in a new handle scope. The environment is borrowed for the
+    /// duration of the scope, so values can only be created through the inner
+    /// environment passed to `f`, and none of them can be returned from it.
+    pub fn scope<F, R>(&mut self, f: F) -> Result<R>
+    where
+        F: for<'a> FnOnce(&mut Env<'a>) -> Result<R>,
+    {
+        let _scope = Scope::new(self.ptr)?;
 
-#[derive(Debug)]
-pub struct Boolean(Value);
+        f(&mut Env::from_raw(self.ptr))
+    }
 
-impl Boolean {
-    pub fn new(env: &Env, value: bool) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+    /// Runs `f` in a new escapable handle scope, returning the value that `f`
+    /// escapes from it through `EscapableScope::escape()`.
+    pub fn escapable_scope<F>(&mut self, f: F) -> Result<Value<'s>>
+    where
+        F: for<'a> FnOnce(EscapableScope<'a, 's>) -> Result<Value<'s>>,
+    {
+        let mut ptr: *mut js_escapable_handle_scope_t = ptr::null_mut();
 
-        unsafe {
-            js_get_boolean(env.ptr, value, &mut ptr);
+        let status = unsafe { js_open_escapable_handle_scope(self.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        // Closes the scope once `f` has returned, including when it panics.
+        struct Guard(*mut js_env_t, *mut js_escapable_handle_scope_t);
+
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                unsafe {
+                    js_close_escapable_handle_scope(self.0, self.1);
+                }
+            }
         }
 
-        Self(Value { env: env.ptr, ptr })
+        let _guard = Guard(self.ptr, ptr);
+
+        f(EscapableScope {
+            env: Env::from_raw(self.ptr),
+            ptr,
+            parent: PhantomData,
+        })
     }
-}
 
-value_conversions!(Boolean);
+    // Runs `f` in a new handle scope on behalf of the event loop rather than
+    // JavaScript, reporting exceptions that it leaves pending as uncaught.
+    fn run_callback<F>(env: *mut js_env_t, f: F)
+    where
+        F: for<'a> FnOnce(&mut Env<'a>) -> Result<()>,
+    {
+        Env::run_detached(env, || {
+            let _root = registry::Root::new(env);
 
-impl From<Boolean> for bool {
-    fn from(boolean: Boolean) -> Self {
-        let mut value = false;
+            f(&mut Env::from_raw(env))
+        });
+    }
 
-        unsafe {
-            js_get_value_bool(boolean.0.env, boolean.0.ptr, &mut value);
-        }
+    // Like `run_callback()`, but without an `Env` so that `f` may open scopes
+    // through an `EnvHandle`.
+    fn run_detached<F>(env: *mut js_env_t, f: F)
+    where
+        F: FnOnce() -> Result<()>,
+    {
+        let Ok(_scope) = Scope::new(env) else {
+            return;
+        };
 
-        value
-    }
-}
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f().map(|()| ptr::null_mut())));
 
-#[derive(Debug)]
-pub struct Number(Value);
+        settle(env, result);
 
-impl Number {
-    pub fn with_i32(env: &Env, value: i32) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+        let env = Env::from_raw(env);
 
-        unsafe {
-            js_create_int32(env.ptr, value, &mut ptr);
+        if let Ok(true) = env.is_exception_pending()
+            && let Ok(error) = env.pending_exception()
+        {
+            unsafe {
+                js_fatal_exception(env.ptr, error.ptr);
+            }
         }
-
-        Self(Value { env: env.ptr, ptr })
     }
 
-    pub fn with_u32(env: &Env, value: u32) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+    // Opens a scope on an environment that no `Env` is handed out for.
+    pub(crate) fn root_scope<F, R>(env: *mut js_env_t, f: F) -> Result<R>
+    where
+        F: for<'a> FnOnce(&mut Env<'a>) -> Result<R>,
+    {
+        let mut root = Env::from_raw(env);
 
-        unsafe {
-            js_create_uint32(env.ptr, value, &mut ptr);
+        if registry::is_rooted(env) {
+            return Err(root.throw(Error::new(
+                &root,
+                "Cannot open a scope through a handle while the environment is in use",
+            )?));
         }
 
-        Self(Value { env: env.ptr, ptr })
-    }
+        let _root = registry::Root::new(env);
 
-    pub fn with_i64(env: &Env, value: i64) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+        root.scope(f)
+    }
 
-        unsafe {
-            js_create_int64(env.ptr, value, &mut ptr);
-        }
+    /// Runs `future` on the JavaScript thread, polling it from the event loop
+    /// until it completes. Exceptions left pending by the future are reported
+    /// as uncaught.
+    pub fn spawn_local<F>(&self, future: F) -> Result<()>
+    where
+        F: Future<Output = Result<()>> + 'static,
+    {
+        local::spawn(self, future)
+    }
 
-        Self(Value { env: env.ptr, ptr })
+    pub fn handle(&self) -> EnvHandle {
+        EnvHandle { ptr: self.ptr }
     }
 
-    pub fn with_f64(env: &Env, value: f64) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+    /// Returns a handle to the tokio runtime of the environment, which is
+    /// started on first use and shut down when the environment is torn down.
+    #[cfg(feature = "tokio")]
+    pub fn tokio_runtime(&self) -> Result<tokio::runtime::Handle> {
+        executor::handle(self)
+    }
 
-        unsafe {
-            js_create_double(env.ptr, value, &mut ptr);
-        }
+    /// Returns the state of type `T` previously stored for the environment.
+    pub fn state<T: 'static>(&self) -> Option<Rc<T>> {
+        registry::state(self)
+    }
 
-        Self(Value { env: env.ptr, ptr })
+    /// Stores `state` for the environment, replacing and returning any previous
+    /// state of the same type. State is dropped when the environment is torn
+    /// down.
+    pub fn set_state<T: 'static>(&self, state: T) -> Result<Option<Rc<T>>> {
+        registry::set_state(self, state)
     }
-}
 
-value_conversions!(Number);
+    /// Registers `f` to run when the environment is torn down.
+    pub fn on_teardown<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce() + 'static,
+    {
+        let closure: TeardownClosure = Box::new(f);
 
-impl From<Number> for i32 {
-    fn from(number: Number) -> Self {
-        let mut value = 0;
+        let data = Box::into_raw(Box::new(closure)) as *mut c_void;
 
-        unsafe {
-            js_get_value_int32(number.0.env, number.0.ptr, &mut value);
+        let status = unsafe { js_add_teardown_callback(self.ptr, Some(teardown), data) };
+
+        if status != 0 {
+            unsafe {
+                drop(Box::from_raw(data as *mut TeardownClosure));
+            }
         }
 
-        value
+        check_status!(status);
+
+        Ok(())
     }
-}
 
-impl From<Number> for u32 {
-    fn from(number: Number) -> Self {
-        let mut value = 0;
+    pub fn uv_loop(&self) -> Result<uv::Loop> {
+        let mut ptr: *mut ffi::uv::uv_loop_t = ptr::null_mut();
 
-        unsafe {
-            js_get_value_uint32(number.0.env, number.0.ptr, &mut value);
-        }
+        let status = unsafe { js_get_env_loop(self.ptr, &mut ptr) };
 
-        value
+        check_status!(status);
+
+        Ok(uv::Loop::from_raw(ptr))
     }
 }
 
-impl From<Number> for i64 {
-    fn from(number: Number) -> Self {
-        let mut value = 0;
-
-        unsafe {
-            js_get_value_int64(number.0.env, number.0.ptr, &mut value);
-        }
+extern "C" fn teardown(data: *mut c_void) {
+    let closure = unsafe { Box::from_raw(data as *mut TeardownClosure) };
 
-        value
+    // There is no JavaScript left to throw into, so under the throw policy a
+    // panic is dropped once the panic hook has reported it.
+    if panic::catch_unwind(AssertUnwindSafe(closure)).is_err()
+        && panic_policy() == PanicPolicy::Abort
+    {
+        process::abort();
     }
 }
 
-impl From<Number> for f64 {
-    fn from(number: Number) -> Self {
-        let mut value = 0.0;
-
-        unsafe {
-            js_get_value_double(number.0.env, number.0.ptr, &mut value);
-        }
+/// A handle to an environment that can be held across await points. Values
+/// cannot be, so they are only made available for the duration of a scope.
+#[derive(Debug, Clone)]
+pub struct EnvHandle {
+    ptr: *mut js_env_t,
+}
 
-        value
+impl EnvHandle {
+    /// Fails if another `Env` for the environment is live, such as inside a
+    /// function callback or another scope, as values created through it would
+    /// otherwise be released along with this scope.
+    pub fn scope<F, R>(&self, f: F) -> Result<R>
+    where
+        F: for<'a> FnOnce(&mut Env<'a>) -> Result<R>,
+    {
+        Env::root_scope(self.ptr, f)
     }
 }
 
-#[derive(Debug)]
-pub struct BigInt(Value);
+// Closes a handle scope when dropped, including when unwinding.
+struct Scope {
+    env: *mut js_env_t,
+    ptr: *mut js_handle_scope_t,
+}
 
-impl BigInt {
-    pub fn with_i64(env: &Env, value: i64) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+impl Scope {
+    fn new(env: *mut js_env_t) -> Result<Self> {
+        let mut ptr: *mut js_handle_scope_t = ptr::null_mut();
 
-        unsafe {
-            js_create_bigint_int64(env.ptr, value, &mut ptr);
-        }
+        let status = unsafe { js_open_handle_scope(env, &mut ptr) };
 
-        Self(Value { env: env.ptr, ptr })
-    }
+        check_status!(status);
 
-    pub fn with_u64(env: &Env, value: u64) -> Self {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+        Ok(Self { env, ptr })
+    }
+}
 
+impl Drop for Scope {
+    fn drop(&mut self) {
         unsafe {
-            js_create_bigint_uint64(env.ptr, value, &mut ptr);
+            js_close_handle_scope(self.env, self.ptr);
         }
-
-        Self(Value { env: env.ptr, ptr })
     }
 }
 
-value_conversions!(BigInt);
-
-impl From<BigInt> for i64 {
-    fn from(bigint: BigInt) -> Self {
-        let mut value = 0;
-
-        unsafe {
-            js_get_value_bigint_int64(bigint.0.env, bigint.0.ptr, &mut value, ptr::null_mut());
-        }
-
-        value
-    }
+/// The inner environment of an escapable scope, of which a single value may
+/// escape to the enclosing scope `'s`.
+#[derive(Debug)]
+pub struct EscapableScope<'e, 's> {
+    env: Env<'e>,
+    ptr: *mut js_escapable_handle_scope_t,
+    parent: PhantomData<&'s ()>,
 }
 
-impl From<BigInt> for u64 {
-    fn from(bigint: BigInt) -> Self {
-        let mut value = 0;
+impl<'s> EscapableScope<'_, 's> {
+    pub fn escape<'a, T>(self, escapee: T) -> Result<Value<'s>>
+    where
+        T: Into<Value<'a>>,
+    {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        unsafe {
-            js_get_value_bigint_uint64(bigint.0.env, bigint.0.ptr, &mut value, ptr::null_mut());
-        }
+        let status =
+            unsafe { js_escape_handle(self.env.ptr, self.ptr, escapee.into().ptr, &mut ptr) };
 
-        value
+        check_status!(status);
+
+        Ok(Value::from_raw(self.env.ptr, ptr))
     }
 }
 
-#[derive(Debug)]
-pub struct Name(Value);
-
-value_conversions!(Name);
+impl<'e> Deref for EscapableScope<'e, '_> {
+    type Target = Env<'e>;
 
-impl From<String> for Name {
-    fn from(string: String) -> Self {
-        Name(string.0)
+    fn deref(&self) -> &Env<'e> {
+        &self.env
     }
 }
 
-impl From<Symbol> for Name {
-    fn from(symbol: Symbol) -> Self {
-        Name(symbol.0)
+impl<'e> DerefMut for EscapableScope<'e, '_> {
+    fn deref_mut(&mut self) -> &mut Env<'e> {
+        &mut self.env
     }
 }
 
+/// A separate global realm. Code runs in the context while it is entered, and
+/// values may be passed between contexts where the engine allows it.
 #[derive(Debug)]
-pub struct Symbol(Value);
+pub struct Context {
+    env: *mut js_env_t,
+    ptr: *mut js_context_t,
+    alive: Arc<AtomicBool>,
+}
 
-impl Symbol {
-    pub fn new(env: &Env, description: &str) -> Result<Self> {
-        let description = String::new(env, description)?;
+impl Context {
+    pub fn new(env: &Env) -> Result<Self> {
+        let alive = registry::liveness(env)?;
 
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+        let mut ptr: *mut js_context_t = ptr::null_mut();
 
-        let status = unsafe { js_create_symbol(env.ptr, description.0.ptr, &mut ptr) };
+        let status = unsafe { js_create_context(env.ptr, &mut ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Self(Value { env: env.ptr, ptr }))
+        Ok(Self {
+            env: env.ptr,
+            ptr,
+            alive,
+        })
     }
-}
-
-value_conversions!(Symbol);
-
-#[derive(Debug)]
-pub struct String(Value);
 
-impl String {
-    pub fn new(env: &Env, value: &str) -> Result<Self> {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+    /// Enters the context until the returned scope is dropped.
+    pub fn enter(&self) -> Result<ContextScope<'_>> {
+        if !self.alive.load(Ordering::Acquire) {
+            return Err(JsException::TornDown);
+        }
 
-        let status = unsafe {
-            js_create_string_utf8(
-                env.ptr,
-                value.as_ptr().cast(),
-                value.len() as usize,
-                &mut ptr,
-            )
-        };
+        let status = unsafe { js_enter_context(self.env, self.ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Self(Value { env: env.ptr, ptr }))
+        Ok(ContextScope { context: self })
     }
+}
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut len = 0;
+impl Drop for Context {
+    fn drop(&mut self) {
+        if !self.alive.load(Ordering::Acquire) {
+            return;
+        }
 
         unsafe {
-            js_get_value_string_utf8(self.0.env, self.0.ptr, ptr::null_mut(), 0, &mut len);
+            js_destroy_context(self.env, self.ptr);
         }
+    }
+}
 
-        let mut result = Vec::new();
-
-        result.resize(len, 0);
+#[derive(Debug)]
+pub struct ContextScope<'c> {
+    context: &'c Context,
+}
 
+impl Drop for ContextScope<'_> {
+    fn drop(&mut self) {
         unsafe {
-            js_get_value_string_utf8(self.0.env, self.0.ptr, result.as_mut_ptr(), len, &mut len);
+            js_exit_context(self.context.env, self.context.ptr);
         }
-
-        result
     }
 }
 
-value_conversions!(String);
+// References can only be read through the environment that created them, and
+// only for as long as it is alive.
+fn check_reference(env: &Env, owner: *mut js_env_t, alive: &AtomicBool) -> Result<()> {
+    if !alive.load(Ordering::Acquire) {
+        return Err(JsException::TornDown);
+    }
 
-impl From<String> for string::String {
-    fn from(string: String) -> Self {
-        unsafe { string::String::from_utf8_unchecked(string.to_bytes()) }
+    if env.ptr != owner {
+        return Err(env.throw(Error::new(env, "Reference belongs to another environment")?));
     }
+
+    Ok(())
 }
 
 #[derive(Debug)]
-pub struct Object(Value);
+pub struct Ref {
+    env: *mut js_env_t,
+    ptr: *mut js_ref_t,
+    alive: Arc<AtomicBool>,
+}
 
-impl Object {
-    pub fn new(env: &Env) -> Result<Self> {
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+impl Ref {
+    pub fn new<'a, T>(env: &Env, value: T) -> Result<Self>
+    where
+        T: Into<Value<'a>>,
+    {
+        let alive = registry::liveness(env)?;
 
-        let status = unsafe { js_create_object(env.ptr, &mut ptr) };
+        let mut ptr: *mut js_ref_t = ptr::null_mut();
+
+        let status = unsafe { js_create_reference(env.ptr, value.into().ptr, 1, &mut ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Self(Value { env: env.ptr, ptr }))
+        Ok(Ref {
+            env: env.ptr,
+            ptr,
+            alive,
+        })
     }
 
-    pub fn get_property<N, T>(&self, name: N) -> Result<T>
+    pub fn get<'s, T>(&self, env: &Env<'s>) -> Result<T>
     where
-        N: Into<Name>,
-        T: From<Value>,
+        T: From<Value<'s>>,
     {
-        let env = Env::from(self.0.env);
+        check_reference(env, self.env, &self.alive)?;
 
         let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        let status =
-            unsafe { js_get_property(self.0.env, self.0.ptr, name.into().0.ptr, &mut ptr) };
+        let status = unsafe { js_get_reference_value(env.ptr, self.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Value::from_raw(env.ptr, ptr).into())
+    }
+}
 
-        check_status!(env, status);
+impl Drop for Ref {
+    fn drop(&mut self) {
+        // The reference was released along with its environment.
+        if !self.alive.load(Ordering::Acquire) {
+            return;
+        }
 
-        Ok(Value { env: env.ptr, ptr }.into())
+        unsafe {
+            js_delete_reference(self.env, self.ptr);
+        }
     }
+}
+
+#[derive(Debug)]
+pub struct WeakRef {
+    env: *mut js_env_t,
+    ptr: *mut js_ref_t,
+    alive: Arc<AtomicBool>,
+}
 
-    pub fn get_named_property<T>(&self, name: &str) -> Result<T>
+impl WeakRef {
+    pub fn new<'a, T>(env: &Env, value: T) -> Result<Self>
     where
-        T: From<Value>,
+        T: Into<Value<'a>>,
     {
-        let env = Env::from(self.0.env);
-
-        let key = CString::new(name).unwrap();
+        let alive = registry::liveness(env)?;
 
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+        let mut ptr: *mut js_ref_t = ptr::null_mut();
 
-        let status =
-            unsafe { js_get_named_property(self.0.env, self.0.ptr, key.as_ptr(), &mut ptr) };
+        let status = unsafe { js_create_reference(env.ptr, value.into().ptr, 0, &mut ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Value { env: env.ptr, ptr }.into())
+        Ok(WeakRef {
+            env: env.ptr,
+            ptr,
+            alive,
+        })
     }
 
-    pub fn get_element<T>(&self, index: u32) -> Result<T>
+    pub fn get<'s, T>(&self, env: &Env<'s>) -> Result<Option<T>>
     where
-        T: From<Value>,
+        T: From<Value<'s>>,
     {
-        let env = Env::from(self.0.env);
+        check_reference(env, self.env, &self.alive)?;
 
         let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        let status = unsafe { js_get_element(self.0.env, self.0.ptr, index, &mut ptr) };
+        let status = unsafe { js_get_reference_value(env.ptr, self.ptr, &mut ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Value { env: env.ptr, ptr }.into())
+        if ptr.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(Value::from_raw(env.ptr, ptr).into()))
+        }
     }
+}
 
-    pub fn has_property<N>(&self, name: N) -> Result<bool>
-    where
-        N: Into<Name>,
-    {
-        let env = Env::from(self.0.env);
+impl Drop for WeakRef {
+    fn drop(&mut self) {
+        // The reference was released along with its environment.
+        if !self.alive.load(Ordering::Acquire) {
+            return;
+        }
 
+        unsafe {
+            js_delete_reference(self.env, self.ptr);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Value<'s> {
+    env: *mut js_env_t,
+    ptr: *mut js_value_t,
+    scope: PhantomData<&'s ()>,
+}
+
+impl Value<'_> {
+    fn from_raw(env: *mut js_env_t, ptr: *mut js_value_t) -> Self {
+        Self {
+            env,
+            ptr,
+            scope: PhantomData,
+        }
+    }
+
+    /// Iterates the value by following the `Symbol.iterator` protocol, which
+    /// accepts any iterable such as an array, string, `Map`, `Set` or generator.
+    /// If the iterator is dropped before it is done, its `return()` method is
+    /// called to let it release any resources.
+    pub fn iter<'e, 't>(&self, env: &'e Env<'t>) -> Result<Iter<'e, 't>> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_coerce_to_object(env.ptr, self.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        let method: Value =
+            Object(Value::from_raw(env.ptr, ptr)).get_property(env, Symbol::iterator(env)?)?;
+
+        if !method.is_function()? {
+            return Err(env.throw(TypeError::new(env, "Value is not iterable")?));
+        }
+
+        let iterator: Value =
+            Function::from(method).call(env, Value::from_raw(self.env, self.ptr), &[])?;
+
+        if !iterator.is_object()? {
+            return Err(env.throw(TypeError::new(env, "Iterator is not an object")?));
+        }
+
+        Ok(Iter::new(env, iterator))
+    }
+
+    fn is_object(&self) -> Result<bool> {
         let mut result = false;
 
-        let status =
-            unsafe { js_has_property(self.0.env, self.0.ptr, name.into().0.ptr, &mut result) };
+        let status = unsafe { js_is_object(self.env, self.ptr, &mut result) };
 
-        check_status!(env, status);
+        check_status!(status);
 
         Ok(result)
     }
 
-    pub fn has_own_property<N>(&self, name: N) -> Result<bool>
-    where
-        N: Into<Name>,
-    {
-        let env = Env::from(self.0.env);
-
+    fn is_function(&self) -> Result<bool> {
         let mut result = false;
 
-        let status =
-            unsafe { js_has_own_property(self.0.env, self.0.ptr, name.into().0.ptr, &mut result) };
+        let status = unsafe { js_is_function(self.env, self.ptr, &mut result) };
 
-        check_status!(env, status);
+        check_status!(status);
 
         Ok(result)
     }
 
-    pub fn has_named_property(&self, name: &str) -> Result<bool> {
-        let env = Env::from(self.0.env);
+    fn is_boolean(&self) -> Result<bool> {
+        let mut result = false;
+
+        let status = unsafe { js_is_boolean(self.env, self.ptr, &mut result) };
 
-        let key = CString::new(name).unwrap();
+        check_status!(status);
 
+        Ok(result)
+    }
+
+    fn is_number(&self) -> Result<bool> {
         let mut result = false;
 
-        let status =
-            unsafe { js_has_named_property(self.0.env, self.0.ptr, key.as_ptr(), &mut result) };
+        let status = unsafe { js_is_number(self.env, self.ptr, &mut result) };
 
-        check_status!(env, status);
+        check_status!(status);
 
         Ok(result)
     }
 
-    pub fn has_element(&self, index: u32) -> Result<bool> {
-        let env = Env::from(self.0.env);
-
+    fn is_symbol(&self) -> Result<bool> {
         let mut result = false;
 
-        let status = unsafe { js_has_element(self.0.env, self.0.ptr, index, &mut result) };
+        let status = unsafe { js_is_symbol(self.env, self.ptr, &mut result) };
 
-        check_status!(env, status);
+        check_status!(status);
 
         Ok(result)
     }
 
-    pub fn set_property<N, T>(&mut self, name: N, value: T) -> Result<()>
-    where
-        N: Into<Name>,
-        T: Into<Value>,
-    {
-        let env = Env::from(self.0.env);
+    fn type_error(&self, message: &str) -> Result<JsException> {
+        let env = Env::from_raw(self.env);
+
+        Ok(env.throw(TypeError::new(&env, message)?))
+    }
+}
+
+impl From<Value<'_>> for *mut js_value_t {
+    fn from(value: Value<'_>) -> Self {
+        value.ptr
+    }
+}
+
+macro_rules! value_conversions {
+    ($type: ident) => {
+        impl From<$type<'_>> for *mut js_value_t {
+            fn from(value: $type<'_>) -> Self {
+                value.0.ptr
+            }
+        }
+
+        impl<'s> From<$type<'s>> for Value<'s> {
+            fn from(value: $type<'s>) -> Self {
+                value.0
+            }
+        }
+
+        impl<'s> From<Value<'s>> for $type<'s> {
+            fn from(value: Value<'s>) -> Self {
+                Self(value)
+            }
+        }
+    };
+}
+
+/// Rust values that may be converted to JavaScript values.
+pub trait IntoValue {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>>;
+}
+
+impl IntoValue for () {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(Undefined::new(env)?.into())
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(Boolean::new(env, self)?.into())
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(String::new(env, self)?.into())
+    }
+}
+
+impl IntoValue for string::String {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(String::new(env, &self)?.into())
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        match self {
+            Some(value) => value.into_value(env),
+            None => Ok(Null::new(env)?.into()),
+        }
+    }
+}
+
+impl<K: IntoValue, V: IntoValue, S> IntoValue for HashMap<K, V, S> {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(Map::from_entries(env, self)?.into())
+    }
+}
+
+impl<K: IntoValue, V: IntoValue> IntoValue for BTreeMap<K, V> {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(Map::from_entries(env, self)?.into())
+    }
+}
+
+impl<T: IntoValue, S> IntoValue for HashSet<T, S> {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(Set::from_values(env, self)?.into())
+    }
+}
+
+impl<T: IntoValue> IntoValue for BTreeSet<T> {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(Set::from_values(env, self)?.into())
+    }
+}
+
+#[derive(Debug)]
+pub struct Undefined<'s>(Value<'s>);
+
+impl<'s> Undefined<'s> {
+    pub fn new(env: &Env<'s>) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_get_undefined(env.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+}
+
+value_conversions!(Undefined);
+
+#[derive(Debug)]
+pub struct Null<'s>(Value<'s>);
+
+impl<'s> Null<'s> {
+    pub fn new(env: &Env<'s>) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_get_null(env.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+}
+
+value_conversions!(Null);
+
+#[derive(Debug)]
+pub struct Boolean<'s>(Value<'s>);
+
+impl<'s> Boolean<'s> {
+    pub fn new(env: &Env<'s>, value: bool) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_get_boolean(env.ptr, value, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+}
+
+value_conversions!(Boolean);
+
+impl TryFrom<Boolean<'_>> for bool {
+    type Error = JsException;
+
+    fn try_from(boolean: Boolean<'_>) -> Result<Self> {
+        let mut value = false;
+
+        let status = unsafe { js_get_value_bool(boolean.0.env, boolean.0.ptr, &mut value) };
+
+        check_status!(status);
+
+        Ok(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct Number<'s>(Value<'s>);
+
+impl<'s> Number<'s> {
+    /// Creates a number using the most compact representation of `value`, which
+    /// may be any Rust numeric type. Integers that a number cannot represent
+    /// exactly fail with a `RangeError`.
+    pub fn new<T: NumberValue>(env: &Env<'s>, value: T) -> Result<Self> {
+        value.into_number(env)
+    }
+
+    pub fn with_i32(env: &Env<'s>, value: i32) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_int32(env.ptr, value, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn with_u32(env: &Env<'s>, value: u32) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_uint32(env.ptr, value, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn with_i64(env: &Env<'s>, value: i64) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_int64(env.ptr, value, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn with_f64(env: &Env<'s>, value: f64) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_double(env.ptr, value, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    // The conversions below fail with a `RangeError` rather than truncating if
+    // the number is not an integer representable by the target type.
+
+    pub fn try_to_i32(&self) -> Result<i32> {
+        let mut is_int32 = false;
+
+        let status = unsafe { js_is_int32(self.0.env, self.0.ptr, &mut is_int32) };
+
+        check_status!(status);
+
+        if !is_int32 {
+            return Err(self.out_of_range()?);
+        }
+
+        let mut value = 0;
+
+        let status = unsafe { js_get_value_int32(self.0.env, self.0.ptr, &mut value) };
+
+        check_status!(status);
+
+        Ok(value)
+    }
+
+    pub fn try_to_u32(&self) -> Result<u32> {
+        let mut is_uint32 = false;
+
+        let status = unsafe { js_is_uint32(self.0.env, self.0.ptr, &mut is_uint32) };
+
+        check_status!(status);
+
+        if !is_uint32 {
+            return Err(self.out_of_range()?);
+        }
+
+        let mut value = 0;
+
+        let status = unsafe { js_get_value_uint32(self.0.env, self.0.ptr, &mut value) };
+
+        check_status!(status);
+
+        Ok(value)
+    }
+
+    pub fn try_to_i64(&self) -> Result<i64> {
+        let value = self.to_f64()?;
+
+        // 2^63 is exactly representable as a double, unlike `i64::MAX`.
+        if value.fract() == 0.0 && value >= -(2f64.powi(63)) && value < 2f64.powi(63) {
+            Ok(value as i64)
+        } else {
+            Err(self.out_of_range()?)
+        }
+    }
+
+    pub fn try_to_u64(&self) -> Result<u64> {
+        let value = self.to_f64()?;
+
+        if value.fract() == 0.0 && value >= 0.0 && value < 2f64.powi(64) {
+            Ok(value as u64)
+        } else {
+            Err(self.out_of_range()?)
+        }
+    }
+
+    fn to_f64(&self) -> Result<f64> {
+        let mut value = 0.0;
+
+        let status = unsafe { js_get_value_double(self.0.env, self.0.ptr, &mut value) };
+
+        check_status!(status);
+
+        Ok(value)
+    }
+
+    fn out_of_range(&self) -> Result<JsException> {
+        let env = Env::from_raw(self.0.env);
+
+        Ok(env.throw(RangeError::new(&env, "Number is out of range")?))
+    }
+}
+
+value_conversions!(Number);
+
+/// Rust numeric types that may be converted to a JavaScript number.
+pub trait NumberValue: Copy {
+    fn into_number<'s>(self, env: &Env<'s>) -> Result<Number<'s>>;
+}
+
+// Integers beyond `Number.MAX_SAFE_INTEGER` would be rounded and must be passed
+// as a `BigInt` instead.
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+fn integer_number<'s>(env: &Env<'s>, value: i128) -> Result<Number<'s>> {
+    if let Ok(value) = i32::try_from(value) {
+        Number::with_i32(env, value)
+    } else if let Ok(value) = u32::try_from(value) {
+        Number::with_u32(env, value)
+    } else if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&value) {
+        Number::with_i64(env, value as i64)
+    } else {
+        Err(unsafe_integer(env)?)
+    }
+}
+
+fn unsafe_integer(env: &Env) -> Result<JsException> {
+    Ok(env.throw(RangeError::new(
+        env,
+        "Integer cannot be represented exactly as a number",
+    )?))
+}
+
+macro_rules! integer_number {
+    ($($type:ident),*) => {
+        $(
+            impl NumberValue for $type {
+                fn into_number<'s>(self, env: &Env<'s>) -> Result<Number<'s>> {
+                    integer_number(env, self as i128)
+                }
+            }
+
+            impl IntoValue for $type {
+                fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+                    Ok(self.into_number(env)?.into())
+                }
+            }
+        )*
+    };
+}
+
+integer_number!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, usize);
+
+impl NumberValue for u128 {
+    fn into_number<'s>(self, env: &Env<'s>) -> Result<Number<'s>> {
+        match i128::try_from(self) {
+            Ok(value) => integer_number(env, value),
+            Err(_) => Err(unsafe_integer(env)?),
+        }
+    }
+}
+
+impl IntoValue for u128 {
+    fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+        Ok(self.into_number(env)?.into())
+    }
+}
+
+macro_rules! float_number {
+    ($($type:ident),*) => {
+        $(
+            impl NumberValue for $type {
+                fn into_number<'s>(self, env: &Env<'s>) -> Result<Number<'s>> {
+                    let value = self as f64;
+
+                    // Integral values are stored as 32-bit integers where
+                    // possible, taking care to preserve negative zero.
+                    if value.fract() == 0.0
+                        && value >= i32::MIN as f64
+                        && value <= i32::MAX as f64
+                        && !(value == 0.0 && value.is_sign_negative())
+                    {
+                        Number::with_i32(env, value as i32)
+                    } else {
+                        Number::with_f64(env, value)
+                    }
+                }
+            }
+
+            impl IntoValue for $type {
+                fn into_value<'s>(self, env: &Env<'s>) -> Result<Value<'s>> {
+                    Ok(self.into_number(env)?.into())
+                }
+            }
+        )*
+    };
+}
+
+float_number!(f32, f64);
+
+impl TryFrom<Number<'_>> for i32 {
+    type Error = JsException;
+
+    fn try_from(number: Number<'_>) -> Result<Self> {
+        number.try_to_i32()
+    }
+}
+
+impl TryFrom<Number<'_>> for u32 {
+    type Error = JsException;
+
+    fn try_from(number: Number<'_>) -> Result<Self> {
+        number.try_to_u32()
+    }
+}
+
+impl TryFrom<Number<'_>> for i64 {
+    type Error = JsException;
+
+    fn try_from(number: Number<'_>) -> Result<Self> {
+        number.try_to_i64()
+    }
+}
+
+impl TryFrom<Number<'_>> for f64 {
+    type Error = JsException;
+
+    fn try_from(number: Number<'_>) -> Result<Self> {
+        let mut value = 0.0;
+
+        let status = unsafe { js_get_value_double(number.0.env, number.0.ptr, &mut value) };
+
+        check_status!(status);
+
+        Ok(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct BigInt<'s>(Value<'s>);
+
+impl<'s> BigInt<'s> {
+    pub fn with_i64(env: &Env<'s>, value: i64) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_bigint_int64(env.ptr, value, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn with_u64(env: &Env<'s>, value: u64) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_bigint_uint64(env.ptr, value, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn with_i128(env: &Env<'s>, value: i128) -> Result<Self> {
+        let magnitude = value.unsigned_abs();
+
+        Self::with_words(
+            env,
+            value < 0,
+            &[magnitude as u64, (magnitude >> 64) as u64],
+        )
+    }
+
+    pub fn with_u128(env: &Env<'s>, value: u128) -> Result<Self> {
+        Self::with_words(env, false, &[value as u64, (value >> 64) as u64])
+    }
+
+    /// Creates a BigInt from its sign and the words of its magnitude, least
+    /// significant word first.
+    pub fn with_words(env: &Env<'s>, negative: bool, words: &[u64]) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe {
+            js_create_bigint_words(
+                env.ptr,
+                negative as c_int,
+                words.as_ptr(),
+                words.len(),
+                &mut ptr,
+            )
+        };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    #[cfg(feature = "num-bigint")]
+    pub fn with_num_bigint(env: &Env<'s>, value: &num_bigint::BigInt) -> Result<Self> {
+        let (sign, words) = value.to_u64_digits();
+
+        Self::with_words(env, sign == num_bigint::Sign::Minus, &words)
+    }
+
+    /// Returns the sign and the words of the magnitude, least significant word
+    /// first.
+    pub fn to_words(&self) -> Result<(bool, Vec<u64>)> {
+        let mut len = 0;
+
+        let status = unsafe {
+            js_get_value_bigint_words(
+                self.0.env,
+                self.0.ptr,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+                &mut len,
+            )
+        };
+
+        check_status!(status);
+
+        let mut sign: c_int = 0;
+        let mut words = vec![0; len];
+
+        if len > 0 {
+            let status = unsafe {
+                js_get_value_bigint_words(
+                    self.0.env,
+                    self.0.ptr,
+                    &mut sign,
+                    words.as_mut_ptr(),
+                    len,
+                    &mut len,
+                )
+            };
+
+            check_status!(status);
+
+            words.truncate(len);
+        }
+
+        Ok((sign != 0, words))
+    }
+
+    // The conversions below return the value truncated to the width of the
+    // target type along with whether it was represented without loss.
+
+    pub fn to_i64(&self) -> Result<(i64, bool)> {
+        let mut value = 0;
+        let mut lossless = false;
+
+        let status =
+            unsafe { js_get_value_bigint_int64(self.0.env, self.0.ptr, &mut value, &mut lossless) };
+
+        check_status!(status);
+
+        Ok((value, lossless))
+    }
+
+    pub fn to_u64(&self) -> Result<(u64, bool)> {
+        let mut value = 0;
+        let mut lossless = false;
+
+        let status = unsafe {
+            js_get_value_bigint_uint64(self.0.env, self.0.ptr, &mut value, &mut lossless)
+        };
+
+        check_status!(status);
+
+        Ok((value, lossless))
+    }
+
+    pub fn to_i128(&self) -> Result<(i128, bool)> {
+        let (negative, magnitude, lossless) = self.to_magnitude()?;
+
+        if negative {
+            Ok((
+                (magnitude as i128).wrapping_neg(),
+                lossless && magnitude <= i128::MIN.unsigned_abs(),
+            ))
+        } else {
+            Ok((
+                magnitude as i128,
+                lossless && magnitude <= i128::MAX as u128,
+            ))
+        }
+    }
+
+    pub fn to_u128(&self) -> Result<(u128, bool)> {
+        let (negative, magnitude, lossless) = self.to_magnitude()?;
+
+        if negative {
+            Ok((magnitude.wrapping_neg(), lossless && magnitude == 0))
+        } else {
+            Ok((magnitude, lossless))
+        }
+    }
+
+    fn to_magnitude(&self) -> Result<(bool, u128, bool)> {
+        let (negative, words) = self.to_words()?;
+
+        let magnitude = words
+            .iter()
+            .take(2)
+            .rev()
+            .fold(0u128, |magnitude, &word| magnitude << 64 | word as u128);
+
+        let lossless = words.iter().skip(2).all(|&word| word == 0);
+
+        Ok((negative, magnitude, lossless))
+    }
+
+    fn narrow<T>(&self, (value, lossless): (T, bool)) -> Result<T> {
+        if lossless {
+            Ok(value)
+        } else {
+            let env = Env::from_raw(self.0.env);
+
+            Err(env.throw(RangeError::new(&env, "BigInt is out of range")?))
+        }
+    }
+}
+
+value_conversions!(BigInt);
+
+macro_rules! bigint_conversion {
+    ($type:ty, $method:ident) => {
+        /// Fails with a `RangeError` if the value does not fit in the target type.
+        impl TryFrom<BigInt<'_>> for $type {
+            type Error = JsException;
+
+            fn try_from(bigint: BigInt<'_>) -> Result<Self> {
+                bigint.narrow(bigint.$method()?)
+            }
+        }
+    };
+}
+
+bigint_conversion!(i64, to_i64);
+bigint_conversion!(u64, to_u64);
+bigint_conversion!(i128, to_i128);
+bigint_conversion!(u128, to_u128);
+
+#[cfg(feature = "num-bigint")]
+impl TryFrom<BigInt<'_>> for num_bigint::BigInt {
+    type Error = JsException;
+
+    fn try_from(bigint: BigInt<'_>) -> Result<Self> {
+        let (negative, words) = bigint.to_words()?;
+
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+        let sign = if negative {
+            num_bigint::Sign::Minus
+        } else {
+            num_bigint::Sign::Plus
+        };
+
+        Ok(num_bigint::BigInt::from_bytes_le(sign, &bytes))
+    }
+}
+
+#[derive(Debug)]
+pub struct Name<'s>(Value<'s>);
+
+impl<'s> Name<'s> {
+    /// Returns a property key for `name`. Keys are interned per environment, so
+    /// repeated lookups of the same name reuse the key created the first time.
+    pub fn key(env: &Env<'s>, name: &'static str) -> Result<Self> {
+        if let Some(key) = registry::key(env, name) {
+            return key;
+        }
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status =
+            unsafe { js_create_property_key_utf8(env.ptr, name.as_ptr(), name.len(), &mut ptr) };
+
+        check_status!(status);
+
+        registry::set_key(env, name, Ref::new(env, Value::from_raw(env.ptr, ptr))?)?;
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+}
+
+value_conversions!(Name);
+
+impl<'s> From<String<'s>> for Name<'s> {
+    fn from(string: String<'s>) -> Self {
+        Name(string.0)
+    }
+}
+
+impl<'s> From<Symbol<'s>> for Name<'s> {
+    fn from(symbol: Symbol<'s>) -> Self {
+        Name(symbol.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct Symbol<'s>(Value<'s>);
+
+impl<'s> Symbol<'s> {
+    pub fn new(env: &Env<'s>, description: &str) -> Result<Self> {
+        let description = String::new(env, description)?;
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_symbol(env.ptr, description.0.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    /// Returns the symbol registered for `key` in the global symbol registry,
+    /// creating it if needed.
+    pub fn for_key(env: &Env<'s>, key: &str) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status =
+            unsafe { js_symbol_for(env.ptr, key.as_ptr() as *const c_char, key.len(), &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn iterator(env: &Env<'s>) -> Result<Self> {
+        Symbol::well_known(env, "iterator")
+    }
+
+    pub fn async_iterator(env: &Env<'s>) -> Result<Self> {
+        Symbol::well_known(env, "asyncIterator")
+    }
+
+    pub fn dispose(env: &Env<'s>) -> Result<Self> {
+        Symbol::well_known(env, "dispose")
+    }
+
+    pub fn to_string_tag(env: &Env<'s>) -> Result<Self> {
+        Symbol::well_known(env, "toStringTag")
+    }
+
+    fn well_known(env: &Env<'s>, name: &str) -> Result<Self> {
+        let constructor: Object = env.global()?.get_named_property(env, "Symbol")?;
+
+        // `globalThis.Symbol` is writable, so the property may not hold a symbol.
+        let symbol: Value = constructor.get_named_property(env, name)?;
+
+        if !symbol.is_symbol()? {
+            return Err(symbol.type_error("Well-known symbol is not a symbol")?);
+        }
+
+        Ok(Self(symbol))
+    }
+}
+
+value_conversions!(Symbol);
+
+// Strings up to this length in bytes are read without allocating an
+// intermediate buffer.
+const STRING_STACK_BUFFER_LEN: usize = 256;
+
+#[derive(Debug)]
+pub struct String<'s>(Value<'s>);
+
+impl<'s> String<'s> {
+    pub fn new(env: &Env<'s>, value: &str) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status =
+            unsafe { js_create_string_utf8(env.ptr, value.as_ptr().cast(), value.len(), &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self
+            .read_utf8(&mut [0; STRING_STACK_BUFFER_LEN])?
+            .into_owned())
+    }
+
+    pub fn to_string_lossy(&self) -> Result<string::String> {
+        let mut buf = [0; STRING_STACK_BUFFER_LEN];
+
+        let bytes = self.read_utf8(&mut buf)?;
+
+        Ok(string::String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Returns the length of the string in bytes when encoded as UTF-8.
+    pub fn len_utf8(&self) -> Result<usize> {
+        let mut len = 0;
+
+        let status = unsafe {
+            js_get_value_string_utf8(self.0.env, self.0.ptr, ptr::null_mut(), 0, &mut len)
+        };
+
+        check_status!(status);
+
+        Ok(len)
+    }
+
+    /// Writes the string as UTF-8 into `buf`, returning the number of bytes
+    /// written. This is less than `String::len_utf8()` if `buf` is too short.
+    pub fn write_utf8(&self, buf: &mut [u8]) -> Result<usize> {
+        let mut len = 0;
+
+        let status = unsafe {
+            js_get_value_string_utf8(
+                self.0.env,
+                self.0.ptr,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut len,
+            )
+        };
+
+        check_status!(status);
+
+        Ok(len)
+    }
+
+    // Reads the string into `buf` if it fits, only allocating for longer
+    // strings.
+    fn read_utf8<'b>(&self, buf: &'b mut [u8]) -> Result<Cow<'b, [u8]>> {
+        let written = self.write_utf8(buf)?;
+
+        // The engine does not split characters, so the string is only known to
+        // be complete if there was room left for another character.
+        if written + 4 <= buf.len() {
+            return Ok(Cow::Borrowed(&buf[..written]));
+        }
+
+        let len = self.len_utf8()?;
+
+        if len == written {
+            return Ok(Cow::Borrowed(&buf[..written]));
+        }
+
+        let mut result = vec![0; len];
+
+        let written = self.write_utf8(&mut result)?;
+
+        result.truncate(written);
+
+        Ok(Cow::Owned(result))
+    }
+
+    /// Creates a string backed by `value` without copying it into the
+    /// JavaScript heap. The engine may still decide to copy, which is reported
+    /// by the returned flag.
+    pub fn external(env: &Env<'s>, value: &'static str) -> Result<(Self, bool)> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+        let mut copied = false;
+
+        let status = unsafe {
+            js_create_external_string_utf8(
+                env.ptr,
+                value.as_ptr() as *mut _,
+                value.len(),
+                None,
+                ptr::null_mut(),
+                &mut ptr,
+                &mut copied,
+            )
+        };
+
+        check_status!(status);
+
+        Ok((Self(Value::from_raw(env.ptr, ptr)), copied))
+    }
+
+    /// Like `String::external()`, but keeps `value` alive until the engine no
+    /// longer needs it.
+    pub fn from_owned(env: &Env<'s>, value: Arc<str>) -> Result<(Self, bool)> {
+        let data = value.as_ptr() as *mut _;
+        let len = value.len();
+
+        let hint = Box::into_raw(Box::new(value)) as *mut c_void;
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+        let mut copied = false;
+
+        let status = unsafe {
+            js_create_external_string_utf8(
+                env.ptr,
+                data,
+                len,
+                Some(String::drop),
+                hint,
+                &mut ptr,
+                &mut copied,
+            )
+        };
+
+        if status != 0 {
+            unsafe {
+                drop(Box::from_raw(hint as *mut Arc<str>));
+            }
+        }
+
+        check_status!(status);
+
+        Ok((Self(Value::from_raw(env.ptr, ptr)), copied))
+    }
+
+    extern "C" fn drop(_: *mut js_env_t, _: *mut c_void, hint: *mut c_void) {
+        unsafe {
+            drop(Box::from_raw(hint as *mut Arc<str>));
+        }
+    }
+
+    pub fn from_utf16le(env: &Env<'s>, value: &[u16]) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status =
+            unsafe { js_create_string_utf16le(env.ptr, value.as_ptr(), value.len(), &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn from_latin1(env: &Env<'s>, value: &[u8]) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status =
+            unsafe { js_create_string_latin1(env.ptr, value.as_ptr(), value.len(), &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn to_utf16le(&self) -> Result<Vec<u16>> {
+        let mut len = 0;
+
+        let status = unsafe {
+            js_get_value_string_utf16le(self.0.env, self.0.ptr, ptr::null_mut(), 0, &mut len)
+        };
+
+        check_status!(status);
+
+        let mut result = vec![0; len];
+
+        let status = unsafe {
+            js_get_value_string_utf16le(self.0.env, self.0.ptr, result.as_mut_ptr(), len, &mut len)
+        };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+
+    /// Characters outside of Latin-1 do not round-trip and are truncated to
+    /// their low byte by the engine.
+    pub fn to_latin1(&self) -> Result<Vec<u8>> {
+        let mut len = 0;
+
+        let status = unsafe {
+            js_get_value_string_latin1(self.0.env, self.0.ptr, ptr::null_mut(), 0, &mut len)
+        };
+
+        check_status!(status);
+
+        let mut result = vec![0; len];
+
+        let status = unsafe {
+            js_get_value_string_latin1(self.0.env, self.0.ptr, result.as_mut_ptr(), len, &mut len)
+        };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+}
+
+value_conversions!(String);
+
+/// Fails with a pending `TypeError` if the string is not valid UTF-8, such as
+/// when it contains lone surrogates. Use `String::to_string_lossy()` to replace
+/// those instead.
+impl TryFrom<String<'_>> for string::String {
+    type Error = JsException;
+
+    fn try_from(string: String<'_>) -> Result<Self> {
+        let mut buf = [0; STRING_STACK_BUFFER_LEN];
+
+        let result = match string.read_utf8(&mut buf)? {
+            Cow::Borrowed(bytes) => str::from_utf8(bytes).map(str::to_owned).ok(),
+            Cow::Owned(bytes) => string::String::from_utf8(bytes).ok(),
+        };
+
+        match result {
+            Some(result) => Ok(result),
+            None => {
+                let env = Env::from_raw(string.0.env);
+
+                Err(env.throw(TypeError::new(&env, "String is not valid UTF-8")?))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Object<'s>(Value<'s>);
+
+impl<'s> Object<'s> {
+    pub fn new(env: &Env<'s>) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_object(env.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn get_property<'a, 't, N, T>(&self, env: &Env<'t>, name: N) -> Result<T>
+    where
+        N: Into<Name<'a>>,
+        T: From<Value<'t>>,
+    {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_get_property(env.ptr, self.0.ptr, name.into().0.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Value::from_raw(env.ptr, ptr).into())
+    }
+
+    pub fn get_named_property<'t, T>(&self, env: &Env<'t>, name: &str) -> Result<T>
+    where
+        T: From<Value<'t>>,
+    {
+        let key = c_string(name)?;
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_get_named_property(env.ptr, self.0.ptr, key.as_ptr(), &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Value::from_raw(env.ptr, ptr).into())
+    }
+
+    pub fn get_element<'t, T>(&self, env: &Env<'t>, index: u32) -> Result<T>
+    where
+        T: From<Value<'t>>,
+    {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_get_element(env.ptr, self.0.ptr, index, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Value::from_raw(env.ptr, ptr).into())
+    }
+
+    pub fn has_property<'a, N>(&self, name: N) -> Result<bool>
+    where
+        N: Into<Name<'a>>,
+    {
+        let mut result = false;
+
+        let status =
+            unsafe { js_has_property(self.0.env, self.0.ptr, name.into().0.ptr, &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+
+    pub fn has_own_property<'a, N>(&self, name: N) -> Result<bool>
+    where
+        N: Into<Name<'a>>,
+    {
+        let mut result = false;
+
+        let status =
+            unsafe { js_has_own_property(self.0.env, self.0.ptr, name.into().0.ptr, &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+
+    pub fn has_named_property(&self, name: &str) -> Result<bool> {
+        let key = c_string(name)?;
+
+        let mut result = false;
+
+        let status =
+            unsafe { js_has_named_property(self.0.env, self.0.ptr, key.as_ptr(), &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+
+    pub fn has_element(&self, index: u32) -> Result<bool> {
+        let mut result = false;
+
+        let status = unsafe { js_has_element(self.0.env, self.0.ptr, index, &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+
+    pub fn set_property<'a, 'b, N, T>(&mut self, name: N, value: T) -> Result<()>
+    where
+        N: Into<Name<'a>>,
+        T: Into<Value<'b>>,
+    {
+        let status =
+            unsafe { js_set_property(self.0.env, self.0.ptr, name.into().0.ptr, value.into().ptr) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn set_named_property<'a, T>(&mut self, name: &str, value: T) -> Result<()>
+    where
+        T: Into<Value<'a>>,
+    {
+        let key = c_string(name)?;
+
+        let status = unsafe {
+            js_set_named_property(self.0.env, self.0.ptr, key.as_ptr(), value.into().ptr)
+        };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn set_element<'a, T>(&mut self, index: u32, value: T) -> Result<()>
+    where
+        T: Into<Value<'a>>,
+    {
+        let status = unsafe { js_set_element(self.0.env, self.0.ptr, index, value.into().ptr) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn delete_property<'a, N>(&self, name: N) -> Result<bool>
+    where
+        N: Into<Name<'a>>,
+    {
+        let mut result = false;
+
+        let status =
+            unsafe { js_delete_property(self.0.env, self.0.ptr, name.into().0.ptr, &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+
+    pub fn delete_named_property(&self, name: &str) -> Result<bool> {
+        let key = c_string(name)?;
+
+        let mut result = false;
+
+        let status =
+            unsafe { js_delete_named_property(self.0.env, self.0.ptr, key.as_ptr(), &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+
+    pub fn delete_element(&self, index: u32) -> Result<bool> {
+        let mut result = false;
+
+        let status = unsafe { js_delete_element(self.0.env, self.0.ptr, index, &mut result) };
+
+        check_status!(status);
+
+        Ok(result)
+    }
+}
+
+value_conversions!(Object);
+
+#[derive(Debug)]
+pub struct Array<'s>(Value<'s>);
+
+impl<'s> Array<'s> {
+    pub fn new(env: &Env<'s>, len: usize) -> Result<Self> {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_create_array_with_length(env.ptr, len, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    pub fn len(&self) -> Result<u32> {
+        let mut len = 0;
+
+        let status = unsafe { js_get_array_length(self.0.env, self.0.ptr, &mut len) };
+
+        check_status!(status);
+
+        Ok(len)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    pub fn get<'t, T>(&self, env: &Env<'t>, index: u32) -> Result<T>
+    where
+        T: From<Value<'t>>,
+    {
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_get_element(env.ptr, self.0.ptr, index, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(Value::from_raw(env.ptr, ptr).into())
+    }
+
+    pub fn set<'a, T>(&mut self, index: u32, value: T) -> Result<()>
+    where
+        T: Into<Value<'a>>,
+    {
+        let status = unsafe { js_set_element(self.0.env, self.0.ptr, index, value.into().ptr) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+}
+
+value_conversions!(Array);
+
+// Creates an instance of the global constructor `name` without arguments.
+fn construct<'t>(env: &Env<'t>, name: &str) -> Result<Value<'t>> {
+    let constructor: Function = env.global()?.get_named_property(env, name)?;
+
+    let mut ptr: *mut js_value_t = ptr::null_mut();
+
+    let status = unsafe { js_new_instance(env.ptr, constructor.0.ptr, 0, ptr::null(), &mut ptr) };
+
+    check_status!(status);
+
+    Ok(Value::from_raw(env.ptr, ptr))
+}
+
+// Calls the method `name` of `receiver` with `args`.
+fn call_method<'t>(
+    env: &Env<'t>,
+    receiver: &Value<'_>,
+    name: &str,
+    args: &[Value<'_>],
+) -> Result<Value<'t>> {
+    let method: Function =
+        Object(Value::from_raw(receiver.env, receiver.ptr)).get_named_property(env, name)?;
+
+    method.call(env, Value::from_raw(receiver.env, receiver.ptr), args)
+}
+
+#[derive(Debug)]
+pub struct Map<'s>(Value<'s>);
+
+impl<'s> Map<'s> {
+    pub fn new(env: &Env<'s>) -> Result<Self> {
+        Ok(Self(construct(env, "Map")?))
+    }
+
+    /// Creates a map from `entries`, such as those of a `HashMap` or `BTreeMap`.
+    /// Unlike an object, the map preserves keys that are not strings.
+    pub fn from_entries<I, K, V>(env: &Env<'s>, entries: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: IntoValue,
+        V: IntoValue,
+    {
+        let mut map = Map::new(env)?;
+
+        for (key, value) in entries {
+            map.set(key.into_value(env)?, value.into_value(env)?)?;
+        }
+
+        Ok(map)
+    }
+
+    pub fn size(&self) -> Result<u32> {
+        let env = Env::from_raw(self.0.env);
+
+        let size: Number =
+            Object(Value::from_raw(self.0.env, self.0.ptr)).get_named_property(&env, "size")?;
+
+        size.try_into()
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.size()? == 0)
+    }
+
+    pub fn get<'a, 't, K, T>(&self, env: &Env<'t>, key: K) -> Result<T>
+    where
+        K: Into<Value<'a>>,
+        T: From<Value<'t>>,
+    {
+        Ok(call_method(env, &self.0, "get", &[key.into()])?.into())
+    }
+
+    pub fn set<'a, 'b, K, T>(&mut self, key: K, value: T) -> Result<()>
+    where
+        K: Into<Value<'a>>,
+        T: Into<Value<'b>>,
+    {
+        let env = Env::from_raw(self.0.env);
+
+        call_method(&env, &self.0, "set", &[key.into(), value.into()])?;
+
+        Ok(())
+    }
+
+    pub fn has<'a, K>(&self, key: K) -> Result<bool>
+    where
+        K: Into<Value<'a>>,
+    {
+        let env = Env::from_raw(self.0.env);
+
+        Boolean::from(call_method(&env, &self.0, "has", &[key.into()])?).try_into()
+    }
+
+    pub fn delete<'a, K>(&self, key: K) -> Result<bool>
+    where
+        K: Into<Value<'a>>,
+    {
+        let env = Env::from_raw(self.0.env);
+
+        Boolean::from(call_method(&env, &self.0, "delete", &[key.into()])?).try_into()
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        let env = Env::from_raw(self.0.env);
+
+        call_method(&env, &self.0, "clear", &[])?;
+
+        Ok(())
+    }
+
+    pub fn keys<'e, 't>(&self, env: &'e Env<'t>) -> Result<Iter<'e, 't>> {
+        Ok(Iter::new(env, call_method(env, &self.0, "keys", &[])?))
+    }
+
+    pub fn values<'e, 't>(&self, env: &'e Env<'t>) -> Result<Iter<'e, 't>> {
+        Ok(Iter::new(env, call_method(env, &self.0, "values", &[])?))
+    }
+
+    /// Iterates the entries of the map as key and value pairs, in insertion
+    /// order.
+    pub fn entries<'e, 't>(
+        &self,
+        env: &'e Env<'t>,
+    ) -> Result<impl Iterator<Item = Result<(Value<'t>, Value<'t>)>> + 'e> {
+        let entries = Iter::new(env, call_method(env, &self.0, "entries", &[])?);
+
+        Ok(entries.map(move |entry| {
+            let entry = Array::from(entry?);
+
+            Ok((entry.get(env, 0)?, entry.get(env, 1)?))
+        }))
+    }
+}
+
+value_conversions!(Map);
+
+#[derive(Debug)]
+pub struct Set<'s>(Value<'s>);
+
+impl<'s> Set<'s> {
+    pub fn new(env: &Env<'s>) -> Result<Self> {
+        Ok(Self(construct(env, "Set")?))
+    }
+
+    /// Creates a set from `values`, such as those of a `HashSet` or `BTreeSet`.
+    pub fn from_values<I, T>(env: &Env<'s>, values: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoValue,
+    {
+        let mut set = Set::new(env)?;
+
+        for value in values {
+            set.add(value.into_value(env)?)?;
+        }
+
+        Ok(set)
+    }
+
+    pub fn size(&self) -> Result<u32> {
+        let env = Env::from_raw(self.0.env);
+
+        let size: Number =
+            Object(Value::from_raw(self.0.env, self.0.ptr)).get_named_property(&env, "size")?;
+
+        size.try_into()
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.size()? == 0)
+    }
+
+    pub fn add<'a, T>(&mut self, value: T) -> Result<()>
+    where
+        T: Into<Value<'a>>,
+    {
+        let env = Env::from_raw(self.0.env);
+
+        call_method(&env, &self.0, "add", &[value.into()])?;
+
+        Ok(())
+    }
+
+    pub fn has<'a, T>(&self, value: T) -> Result<bool>
+    where
+        T: Into<Value<'a>>,
+    {
+        let env = Env::from_raw(self.0.env);
+
+        Boolean::from(call_method(&env, &self.0, "has", &[value.into()])?).try_into()
+    }
+
+    pub fn delete<'a, T>(&self, value: T) -> Result<bool>
+    where
+        T: Into<Value<'a>>,
+    {
+        let env = Env::from_raw(self.0.env);
+
+        Boolean::from(call_method(&env, &self.0, "delete", &[value.into()])?).try_into()
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        let env = Env::from_raw(self.0.env);
+
+        call_method(&env, &self.0, "clear", &[])?;
+
+        Ok(())
+    }
+
+    pub fn values<'e, 't>(&self, env: &'e Env<'t>) -> Result<Iter<'e, 't>> {
+        Ok(Iter::new(env, call_method(env, &self.0, "values", &[])?))
+    }
+}
+
+value_conversions!(Set);
+
+/// Iterates a JavaScript iterator object, yielding the values it produces until
+/// it is done or fails.
+#[derive(Debug)]
+pub struct Iter<'e, 't> {
+    env: &'e Env<'t>,
+    iterator: Value<'t>,
+    done: bool,
+}
+
+impl<'e, 't> Iter<'e, 't> {
+    fn new(env: &'e Env<'t>, iterator: Value<'t>) -> Self {
+        Self {
+            env,
+            iterator,
+            done: false,
+        }
+    }
+
+    /// Closes the iterator before it is done, returning any exception thrown by
+    /// its `return()` method. Dropping the iterator closes it as well, but
+    /// leaves such an exception pending.
+    pub fn close(mut self) -> Result<()> {
+        self.done = true;
+
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let method: Value = Object(Value::from_raw(self.env.ptr, self.iterator.ptr))
+            .get_named_property(self.env, "return")?;
+
+        if method.is_function()? {
+            Function::from(method).call::<_, _, Value>(
+                self.env,
+                Value::from_raw(self.env.ptr, self.iterator.ptr),
+                &[],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Option<Value<'t>>> {
+        let result = call_method(self.env, &self.iterator, "next", &[])?;
+
+        if !result.is_object()? {
+            return Err(self.env.throw(TypeError::new(
+                self.env,
+                "Iterator result is not an object",
+            )?));
+        }
+
+        let result = Object::from(result);
+
+        let done: Boolean = result.get_named_property(self.env, "done")?;
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe { js_coerce_to_boolean(self.env.ptr, done.0.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        if bool::try_from(Boolean(Value::from_raw(self.env.ptr, ptr)))? {
+            Ok(None)
+        } else {
+            Ok(Some(result.get_named_property(self.env, "value")?))
+        }
+    }
+}
+
+impl<'t> Iterator for Iter<'_, 't> {
+    type Item = Result<Value<'t>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.step() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.done = true;
+
+                None
+            }
+            Err(err) => {
+                self.done = true;
+
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Drop for Iter<'_, '_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        // An exception that is already pending, such as one propagated by the
+        // code consuming the iterator, takes precedence over one thrown while
+        // closing it.
+        let pending = match self.env.is_exception_pending() {
+            Ok(true) => self.env.pending_exception().ok(),
+            _ => None,
+        };
+
+        let result = self.finish();
+
+        if let Some(exception) = pending {
+            if result.is_err() {
+                let _ = self.env.pending_exception();
+            }
+
+            self.env.throw(exception);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Callback<'s> {
+    env: *mut js_env_t,
+    args: Vec<*mut js_value_t>,
+    receiver: *mut js_value_t,
+    scope: PhantomData<&'s ()>,
+}
+
+impl<'s> Callback<'s> {
+    pub fn arg<T>(&self, i: usize) -> Option<T>
+    where
+        T: From<Value<'s>>,
+    {
+        if i < self.args.len() {
+            Some(self.arg_unchecked(i))
+        } else {
+            None
+        }
+    }
+
+    pub fn arg_unchecked<T>(&self, i: usize) -> T
+    where
+        T: From<Value<'s>>,
+    {
+        Value::from_raw(self.env, self.args[i]).into()
+    }
+
+    pub fn receiver<T>(&self) -> T
+    where
+        T: From<Value<'s>>,
+    {
+        Value::from_raw(self.env, self.receiver).into()
+    }
+}
+
+type FunctionClosure = Box<dyn for<'s> FnMut(&mut Env<'s>, &Callback<'s>) -> Result<Value<'s>>>;
+
+#[derive(Debug)]
+pub struct Function<'s>(Value<'s>);
+
+impl<'s> Function<'s> {
+    pub fn new<F>(env: &Env<'s>, function: F) -> Result<Self>
+    where
+        F: for<'a> FnMut(&mut Env<'a>, &Callback<'a>) -> Result<Value<'a>> + 'static,
+    {
+        Function::create(env, Box::new(function), None)
+    }
+
+    /// Creates a function with a fast path that the engine may call directly
+    /// with unboxed arguments, for example `Function::typed::<fn(i32, f64) ->
+    /// f64>(env, kernel)`. `function` must not capture any state. Calls that
+    /// cannot take the fast path convert their arguments and go through the
+    /// regular callback instead. A panic on the fast path aborts the process
+    /// regardless of the panic policy.
+    pub fn typed<S>(env: &Env<'s>, function: impl TypedFunction<S>) -> Result<Self> {
+        fn typed<'s, S, F: TypedFunction<S>>(env: &Env<'s>, function: F) -> Result<Function<'s>> {
+            let mut args = F::ARGS.to_vec();
+
+            let signature = js_callback_signature_t {
+                version: 0,
+                result: F::RESULT,
+                args_len: args.len(),
+                args: args.as_mut_ptr(),
+            };
+
+            Function::create(
+                env,
+                Box::new(move |env, callback| function.call(env, callback)),
+                Some((&signature, F::address())),
+            )
+        }
+
+        typed(env, function)
+    }
+
+    fn create(
+        env: &Env<'s>,
+        closure: FunctionClosure,
+        typed: Option<(&js_callback_signature_t, *const c_void)>,
+    ) -> Result<Self> {
+        let data = Box::into_raw(Box::new(closure)) as *mut _;
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe {
+            match typed {
+                Some((signature, address)) => js_create_typed_function(
+                    env.ptr,
+                    ptr::null_mut(),
+                    0,
+                    Some(Function::apply),
+                    signature,
+                    address,
+                    data,
+                    &mut ptr,
+                ),
+                None => js_create_function(
+                    env.ptr,
+                    ptr::null_mut(),
+                    0,
+                    Some(Function::apply),
+                    data,
+                    &mut ptr,
+                ),
+            }
+        };
+
+        check_status!(status);
+
+        let status = unsafe {
+            js_add_finalizer(
+                env.ptr,
+                ptr,
+                data,
+                Some(Function::drop),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    /// Compiles a function with the given parameter names and body. `filename`
+    /// and `line_offset` are used for stack traces.
+    pub fn from_source(
+        env: &Env<'s>,
+        name: &str,
+        params: &[&str],
+        body: &str,
+        filename: &str,
+        line_offset: i32,
+    ) -> Result<Self> {
+        let params = params
+            .iter()
+            .map(|param| Ok(String::new(env, param)?.0.ptr))
+            .collect::<Result<Vec<_>>>()?;
+
+        let body = String::new(env, body)?;
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe {
+            js_create_function_with_source(
+                env.ptr,
+                name.as_ptr() as *const c_char,
+                name.len(),
+                filename.as_ptr() as *const c_char,
+                filename.len(),
+                params.as_ptr(),
+                params.len(),
+                line_offset,
+                body.0.ptr,
+                &mut ptr,
+            )
+        };
+
+        check_status!(status);
+
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
+    }
+
+    /// Creates a function returning a promise that settles with the output of
+    /// the future returned by `function`. The future runs on the tokio runtime
+    /// of the environment and its output is passed to `convert` back on the
+    /// JavaScript thread.
+    #[cfg(feature = "tokio")]
+    pub fn new_async<F, T, C>(env: &Env<'s>, mut function: F, convert: C) -> Result<Self>
+    where
+        F: for<'a> FnMut(&mut Env<'a>, &Callback<'a>) -> Result<T> + 'static,
+        T: Future + Send + 'static,
+        T::Output: Send + 'static,
+        C: for<'a> Fn(&mut Env<'a>, T::Output) -> Result<Value<'a>> + 'static,
+    {
+        let convert = Rc::new(convert);
+
+        Function::new(env, move |env, callback| {
+            let future = function(env, callback)?;
+
+            let (promise, deferred) = Promise::new(env)?;
+
+            let convert = convert.clone();
+
+            executor::spawn(env, future, move |env, output| {
+                let result = match output {
+                    Ok(output) => convert(env, output),
+                    Err(payload) => {
+                        if panic_policy() == PanicPolicy::Abort {
+                            process::abort();
+                        }
+
+                        throw_message(env, panic_message(payload.as_ref()));
+
+                        Err(JsException::Pending)
+                    }
+                };
+
+                deferred.settle(env, result)
+            })?;
+
+            Ok(promise.into())
+        })
+    }
+
+    pub fn call<'a, 'b, 't, C, A, R>(&self, env: &Env<'t>, receiver: C, args: A) -> Result<R>
+    where
+        C: Into<Value<'a>>,
+        A: IntoIterator<Item = &'b Value<'b>>,
+        R: From<Value<'t>>,
+    {
+        let mut args: Vec<_> = args.into_iter().map(|value| value.ptr).collect();
+
+        let mut ptr: *mut js_value_t = ptr::null_mut();
+
+        let status = unsafe {
+            js_call_function(
+                env.ptr,
+                receiver.into().ptr,
+                self.0.ptr,
+                args.len(),
+                args.as_mut_ptr(),
+                &mut ptr,
+            )
+        };
+
+        check_status!(status);
+
+        Ok(Value::from_raw(env.ptr, ptr).into())
+    }
+
+    extern "C" fn apply(env: *mut js_env_t, info: *mut js_callback_info_t) -> *mut js_value_t {
+        let mut len: usize = 0;
+        let mut receiver: *mut js_value_t = ptr::null_mut();
+        let mut data: *mut c_void = ptr::null_mut();
+
+        let mut status = unsafe {
+            js_get_callback_info(
+                env,
+                info,
+                &mut len,
+                ptr::null_mut(),
+                &mut receiver,
+                &mut data,
+            )
+        };
+
+        let mut args = Vec::new();
+
+        if status == 0 && len > 0 {
+            args.resize(len, ptr::null_mut());
+
+            status = unsafe {
+                js_get_callback_info(
+                    env,
+                    info,
+                    &mut len,
+                    args.as_mut_ptr(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+        }
+
+        if status != 0 {
+            return unsafe { catch_panic(env, |_| Err(JsException::from(status))) };
+        }
+
+        let closure = unsafe { &mut *(data as *mut FunctionClosure) };
+
+        unsafe {
+            catch_panic(env, |env| {
+                let callback = Callback {
+                    env: env.ptr,
+                    args,
+                    receiver,
+                    scope: PhantomData,
+                };
+
+                closure(env, &callback)
+            })
+        }
+    }
+
+    extern "C" fn drop(_: *mut js_env_t, data: *mut c_void, _: *mut c_void) {
+        unsafe {
+            drop(Box::from_raw(data as *mut FunctionClosure));
+        }
+    }
+}
+
+value_conversions!(Function);
+
+/// Values that may be passed to and returned from the fast path of a typed
+/// function.
+pub trait TypedValue: IntoValue + Sized {
+    const TYPE: c_int;
+
+    fn from_value(value: Value<'_>) -> Result<Self>;
+}
+
+impl TypedValue for () {
+    const TYPE: c_int = js_value_type_t::js_undefined as c_int;
+
+    fn from_value(_: Value<'_>) -> Result<Self> {
+        Ok(())
+    }
+}
+
+impl TypedValue for bool {
+    const TYPE: c_int = js_value_type_t::js_boolean as c_int;
+
+    fn from_value(value: Value<'_>) -> Result<Self> {
+        if !value.is_boolean()? {
+            return Err(value.type_error("Expected a boolean")?);
+        }
+
+        Boolean::from(value).try_into()
+    }
+}
+
+macro_rules! typed_number {
+    ($type:ident, $value_type:ident, $to:ident) => {
+        impl TypedValue for $type {
+            const TYPE: c_int = js_value_type_t::$value_type as c_int;
+
+            fn from_value(value: Value<'_>) -> Result<Self> {
+                if !value.is_number()? {
+                    return Err(value.type_error("Expected a number")?);
+                }
+
+                Number::from(value).$to()
+            }
+        }
+    };
+}
+
+typed_number!(i32, js_int32, try_to_i32);
+typed_number!(u32, js_uint32, try_to_u32);
+typed_number!(i64, js_int64, try_to_i64);
+typed_number!(f64, js_float64, to_f64);
+
+/// Functions with a signature `S` of the form `fn(A, B, ...) -> R`.
+pub trait TypedFunction<S>: Copy + 'static {
+    const RESULT: c_int;
+
+    const ARGS: &'static [c_int];
+
+    fn address() -> *const c_void;
+
+    fn call<'a>(&self, env: &Env<'a>, callback: &Callback<'a>) -> Result<Value<'a>>;
+}
+
+fn typed_arg<T: TypedValue>(env: &Env, callback: &Callback<'_>, i: usize) -> Result<T> {
+    match callback.args.get(i) {
+        Some(&ptr) => T::from_value(Value::from_raw(env.ptr, ptr)),
+        None => T::from_value(Undefined::new(env)?.into()),
+    }
+}
+
+macro_rules! typed_function {
+    ($($i:tt $arg:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<F, R, $($arg),*> TypedFunction<fn($($arg),*) -> R> for F
+        where
+            F: Fn($($arg),*) -> R + Copy + 'static,
+            R: TypedValue,
+            $($arg: TypedValue),*
+        {
+            const RESULT: c_int = R::TYPE;
+
+            // The receiver is passed as the first argument.
+            const ARGS: &'static [c_int] = &[js_value_type_t::js_object as c_int, $($arg::TYPE),*];
+
+            fn address() -> *const c_void {
+                extern "C" fn fast<F, R, $($arg),*>(
+                    _: *mut js_value_t,
+                    $($arg: $arg,)*
+                    _: *mut js_typed_callback_info_t,
+                ) -> R
+                where
+                    F: Fn($($arg),*) -> R,
+                {
+                    // The function has no state, so a value of it can be
+                    // conjured from nothing.
+                    let function: F = unsafe { mem::zeroed() };
+
+                    // There is no environment to throw into on the fast path.
+                    match panic::catch_unwind(AssertUnwindSafe(|| function($($arg),*))) {
+                        Ok(result) => result,
+                        Err(_) => process::abort(),
+                    }
+                }
+
+                const { assert!(mem::size_of::<F>() == 0, "typed functions must not capture state") };
+
+                fast::<F, R, $($arg),*> as *const c_void
+            }
+
+            fn call<'a>(&self, env: &Env<'a>, callback: &Callback<'a>) -> Result<Value<'a>> {
+                $(let $arg = typed_arg::<$arg>(env, callback, $i)?;)*
+
+                self($($arg),*).into_value(env)
+            }
+        }
+    };
+}
+
+typed_function!();
+typed_function!(0 A);
+typed_function!(0 A, 1 B);
+typed_function!(0 A, 1 B, 2 C);
+typed_function!(0 A, 1 B, 2 C, 3 D);
+typed_function!(0 A, 1 B, 2 C, 3 D, 4 E);
+typed_function!(0 A, 1 B, 2 C, 3 D, 4 E, 5 G);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadsafeFunctionCallMode {
+    Blocking,
+    NonBlocking,
+}
+
+type ThreadsafeFunctionClosure<T> =
+    Box<dyn for<'s> FnMut(&mut Env<'s>, T) -> Result<Vec<Value<'s>>>>;
+
+#[derive(Debug)]
+pub struct ThreadsafeFunction<T> {
+    handle: Arc<ThreadsafeFunctionHandle>,
+    data: PhantomData<fn(T)>,
+}
+
+// An acquisition of a threadsafe function, shared between clones and released
+// once the last of them is dropped unless the function was aborted through it.
+#[derive(Debug)]
+struct ThreadsafeFunctionHandle {
+    ptr: *mut js_threadsafe_function_t,
+    aborted: AtomicBool,
+}
+
+unsafe impl Send for ThreadsafeFunctionHandle {}
+
+unsafe impl Sync for ThreadsafeFunctionHandle {}
+
+impl ThreadsafeFunctionHandle {
+    fn new(ptr: *mut js_threadsafe_function_t) -> Arc<Self> {
+        Arc::new(Self {
+            ptr,
+            aborted: AtomicBool::new(false),
+        })
+    }
+}
+
+impl Drop for ThreadsafeFunctionHandle {
+    fn drop(&mut self) {
+        if !*self.aborted.get_mut() {
+            unsafe {
+                js_release_threadsafe_function(
+                    self.ptr,
+                    js_threadsafe_function_release_mode_t::js_threadsafe_function_release,
+                );
+            }
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for ThreadsafeFunction<T> {}
+
+unsafe impl<T: Send> Sync for ThreadsafeFunction<T> {}
+
+impl<T: Send + 'static> ThreadsafeFunction<T> {
+    pub fn new<F>(env: &Env, function: Function<'_>, convert: F) -> Result<Self>
+    where
+        F: for<'a> FnMut(&mut Env<'a>, T) -> Result<Vec<Value<'a>>> + 'static,
+    {
+        Self::with_queue_limit(env, function, 0, convert)
+    }
+
+    pub fn with_queue_limit<F>(
+        env: &Env,
+        function: Function<'_>,
+        queue_limit: usize,
+        convert: F,
+    ) -> Result<Self>
+    where
+        F: for<'a> FnMut(&mut Env<'a>, T) -> Result<Vec<Value<'a>>> + 'static,
+    {
+        let closure: ThreadsafeFunctionClosure<T> = Box::new(convert);
+
+        let context = Box::into_raw(Box::new(closure)) as *mut c_void;
+
+        let mut ptr: *mut js_threadsafe_function_t = ptr::null_mut();
+
+        let status = unsafe {
+            js_create_threadsafe_function(
+                env.ptr,
+                function.0.ptr,
+                queue_limit,
+                1,
+                Some(ThreadsafeFunction::<T>::finalize),
+                context,
+                context,
+                Some(ThreadsafeFunction::<T>::apply),
+                &mut ptr,
+            )
+        };
+
+        if status != 0 {
+            unsafe {
+                drop(Box::from_raw(context as *mut ThreadsafeFunctionClosure<T>));
+            }
+        }
+
+        check_status!(status);
+
+        Ok(Self {
+            handle: ThreadsafeFunctionHandle::new(ptr),
+            data: PhantomData,
+        })
+    }
+
+    pub fn call(&self, value: T, mode: ThreadsafeFunctionCallMode) -> Result<()> {
+        let mode = match mode {
+            ThreadsafeFunctionCallMode::Blocking => {
+                js_threadsafe_function_call_mode_t::js_threadsafe_function_blocking
+            }
+            ThreadsafeFunctionCallMode::NonBlocking => {
+                js_threadsafe_function_call_mode_t::js_threadsafe_function_nonblocking
+            }
+        };
+
+        let data = Box::into_raw(Box::new(value));
 
         let status =
-            unsafe { js_set_property(self.0.env, self.0.ptr, name.into().0.ptr, value.into().ptr) };
+            unsafe { js_call_threadsafe_function(self.handle.ptr, data as *mut c_void, mode) };
+
+        if status != 0 {
+            unsafe {
+                drop(Box::from_raw(data));
+            }
+        }
 
-        check_status!(env, status);
+        check_status!(status);
 
         Ok(())
     }
 
-    pub fn set_named_property<T>(&mut self, name: &str, value: T) -> Result<()>
-    where
-        T: Into<Value>,
-    {
-        let env = Env::from(self.0.env);
-
-        let key = CString::new(name).unwrap();
-
-        let status = unsafe {
-            js_set_named_property(self.0.env, self.0.ptr, key.as_ptr(), value.into().ptr)
-        };
+    pub fn reference(&self, env: &Env) -> Result<()> {
+        let status = unsafe { js_ref_threadsafe_function(env.ptr, self.handle.ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
         Ok(())
     }
 
-    pub fn set_element<T>(&mut self, index: u32, value: T) -> Result<()>
-    where
-        T: Into<Value>,
-    {
-        let env = Env::from(self.0.env);
-
-        let status = unsafe { js_set_element(self.0.env, self.0.ptr, index, value.into().ptr) };
+    pub fn unreference(&self, env: &Env) -> Result<()> {
+        let status = unsafe { js_unref_threadsafe_function(env.ptr, self.handle.ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
         Ok(())
     }
 
-    pub fn delete_property<N>(&self, name: N) -> Result<bool>
-    where
-        N: Into<Name>,
-    {
-        let env = Env::from(self.0.env);
-
-        let mut result = false;
+    pub fn abort(self) -> Result<()> {
+        if self.handle.aborted.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
 
-        let status =
-            unsafe { js_delete_property(self.0.env, self.0.ptr, name.into().0.ptr, &mut result) };
+        let status = unsafe {
+            js_release_threadsafe_function(
+                self.handle.ptr,
+                js_threadsafe_function_release_mode_t::js_threadsafe_function_abort,
+            )
+        };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(result)
+        Ok(())
     }
 
-    pub fn delete_named_property(&self, name: &str) -> Result<bool> {
-        let env = Env::from(self.0.env);
+    extern "C" fn apply(
+        env: *mut js_env_t,
+        function: *mut js_value_t,
+        context: *mut c_void,
+        data: *mut c_void,
+    ) {
+        let value = unsafe { Box::from_raw(data as *mut T) };
+
+        // Calls still queued when the function is aborted are delivered
+        // without an environment so that their data can be released.
+        if env.is_null() {
+            return;
+        }
 
-        let key = CString::new(name).unwrap();
+        let closure = unsafe { &mut *(context as *mut ThreadsafeFunctionClosure<T>) };
 
-        let mut result = false;
+        Env::run_callback(env, |env| {
+            let args = closure(env, *value)?;
 
-        let status =
-            unsafe { js_delete_named_property(self.0.env, self.0.ptr, key.as_ptr(), &mut result) };
+            let function = Function(Value::from_raw(env.ptr, function));
 
-        check_status!(env, status);
+            function.call::<_, _, Value>(env, Undefined::new(env)?, &args)?;
 
-        Ok(result)
+            Ok(())
+        });
     }
 
-    pub fn delete_element(&self, index: u32) -> Result<bool> {
-        let env = Env::from(self.0.env);
+    extern "C" fn finalize(_: *mut js_env_t, _: *mut c_void, context: *mut c_void) {
+        unsafe {
+            drop(Box::from_raw(context as *mut ThreadsafeFunctionClosure<T>));
+        }
+    }
+}
 
-        let mut result = false;
+impl<T> ThreadsafeFunction<T> {
+    /// Acquires another handle to the function, failing if the function has
+    /// been aborted. Unlike clones, which share a handle, the function stays
+    /// acquired through it until it is dropped.
+    pub fn try_clone(&self) -> Result<Self> {
+        let status = unsafe { js_acquire_threadsafe_function(self.handle.ptr) };
 
-        let status = unsafe { js_delete_element(self.0.env, self.0.ptr, index, &mut result) };
+        check_status!(status);
 
-        check_status!(env, status);
+        Ok(Self {
+            handle: ThreadsafeFunctionHandle::new(self.handle.ptr),
+            data: PhantomData,
+        })
+    }
+}
 
-        Ok(result)
+impl<T> Clone for ThreadsafeFunction<T> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            data: PhantomData,
+        }
     }
 }
 
-value_conversions!(Object);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromiseState {
+    Pending,
+    Fulfilled,
+    Rejected,
+}
 
 #[derive(Debug)]
-pub struct Array(Value);
+pub struct Promise<'s>(Value<'s>);
 
-impl Array {
-    pub fn new(env: &Env, len: usize) -> Result<Self> {
+impl<'s> Promise<'s> {
+    pub fn new(env: &Env<'s>) -> Result<(Self, Deferred)> {
+        let mut deferred: *mut js_deferred_t = ptr::null_mut();
         let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        let status = unsafe { js_create_array_with_length(env.ptr, len, &mut ptr) };
+        let status = unsafe { js_create_promise(env.ptr, &mut deferred, &mut ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Self(Value { env: env.ptr, ptr }))
+        Ok((
+            Self(Value::from_raw(env.ptr, ptr)),
+            Deferred { ptr: deferred },
+        ))
     }
 
-    pub fn len(&self) -> u32 {
-        let mut len = 0;
+    pub fn state(&self) -> Result<PromiseState> {
+        let mut result = js_promise_state_t::js_promise_pending;
 
-        unsafe {
-            js_get_array_length(self.0.env, self.0.ptr, &mut len);
-        }
+        let status = unsafe { js_get_promise_state(self.0.env, self.0.ptr, &mut result) };
 
-        len
-    }
+        check_status!(status);
 
-    pub fn get<T>(&self, index: u32) -> Result<T>
-    where
-        T: From<Value>,
-    {
-        let env = Env::from(self.0.env);
+        Ok(match result {
+            js_promise_state_t::js_promise_pending => PromiseState::Pending,
+            js_promise_state_t::js_promise_fulfilled => PromiseState::Fulfilled,
+            js_promise_state_t::js_promise_rejected => PromiseState::Rejected,
+        })
+    }
 
+    pub fn result<'t>(&self, env: &Env<'t>) -> Result<Value<'t>> {
         let mut ptr: *mut js_value_t = ptr::null_mut();
 
-        let status = unsafe { js_get_element(self.0.env, self.0.ptr, index, &mut ptr) };
+        let status = unsafe { js_get_promise_result(env.ptr, self.0.ptr, &mut ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Value { env: env.ptr, ptr }.into())
+        Ok(Value::from_raw(env.ptr, ptr))
     }
 
-    pub fn set<T>(&mut self, index: u32, value: T) -> Result<()>
-    where
-        T: Into<Value>,
-    {
-        let env = Env::from(self.0.env);
+    /// Returns a future that completes with a reference to the fulfillment
+    /// value of the promise. If the promise is rejected, the rejection reason
+    /// is thrown when the future is polled and `JsException::Pending` returned.
+    pub fn into_future(self) -> Result<PromiseFuture> {
+        let env = Env::from_raw(self.0.env);
 
-        let status = unsafe { js_set_element(self.0.env, self.0.ptr, index, value.into().ptr) };
+        let state = Rc::new(RefCell::new(PromiseFutureState::Pending(None)));
 
-        check_status!(env, status);
+        let on_fulfilled = {
+            let state = state.clone();
 
-        Ok(())
+            Function::new(&env, move |env, callback| {
+                PromiseFuture::settle(
+                    &state,
+                    Ok(Ref::new(env, PromiseFuture::arg(env, callback)?)?),
+                );
+
+                Ok(Undefined::new(env)?.into())
+            })?
+        };
+
+        let on_rejected = {
+            let state = state.clone();
+
+            Function::new(&env, move |env, callback| {
+                PromiseFuture::settle(
+                    &state,
+                    Err(Ref::new(env, PromiseFuture::arg(env, callback)?)?),
+                );
+
+                Ok(Undefined::new(env)?.into())
+            })?
+        };
+
+        let then: Function =
+            Object(Value::from_raw(self.0.env, self.0.ptr)).get_named_property(&env, "then")?;
+
+        then.call::<_, _, Value>(&env, self, &[on_fulfilled.0, on_rejected.0])?;
+
+        Ok(PromiseFuture {
+            env: env.ptr,
+            state,
+        })
     }
 }
 
-value_conversions!(Array);
+value_conversions!(Promise);
+
+#[derive(Debug)]
+enum PromiseFutureState {
+    Pending(Option<Waker>),
+    Settled(result::Result<Ref, Ref>),
+    Done,
+}
 
 #[derive(Debug)]
-pub struct Callback {
+pub struct PromiseFuture {
     env: *mut js_env_t,
-    args: Vec<*mut js_value_t>,
-    receiver: *mut js_value_t,
+    state: Rc<RefCell<PromiseFutureState>>,
 }
 
-impl Callback {
-    pub fn arg<T>(&self, i: usize) -> Option<T>
-    where
-        T: From<Value>,
-    {
-        if i < self.args.len() {
-            Some(self.arg_unchecked(i))
-        } else {
-            None
+impl PromiseFuture {
+    fn arg<'a>(env: &Env<'a>, callback: &Callback<'a>) -> Result<Value<'a>> {
+        match callback.arg(0) {
+            Some(value) => Ok(value),
+            None => Ok(Undefined::new(env)?.into()),
         }
     }
 
-    pub fn arg_unchecked<T>(&self, i: usize) -> T
-    where
-        T: From<Value>,
-    {
-        Value {
-            env: self.env,
-            ptr: self.args[i],
-        }
-        .into()
-    }
+    fn settle(state: &RefCell<PromiseFutureState>, result: result::Result<Ref, Ref>) {
+        let state = mem::replace(
+            &mut *state.borrow_mut(),
+            PromiseFutureState::Settled(result),
+        );
 
-    pub fn receiver<T>(&self) -> T
-    where
-        T: From<Value>,
-    {
-        Value {
-            env: self.env,
-            ptr: self.receiver,
+        if let PromiseFutureState::Pending(Some(waker)) = state {
+            waker.wake();
         }
-        .into()
     }
 }
 
-#[derive(Debug)]
-pub struct Function(Value);
-
-impl Function {
-    pub fn new<F>(env: &Env, function: F) -> Result<Self>
-    where
-        F: FnMut(&Env, &Callback) -> Result<Value>,
-    {
-        let mut function = function;
-
-        let closure: Box<dyn FnMut(&Env, &Callback) -> *mut js_value_t> =
-            Box::new(move |env, info| match function(env, info) {
-                Ok(result) => result.into(),
-                Err(error) => {
-                    unsafe {
-                        js_throw(env.ptr, error.into());
-                    }
-
-                    ptr::null_mut()
-                }
-            });
+impl Future for PromiseFuture {
+    type Output = Result<Ref>;
 
-        let data = Box::into_raw(Box::new(closure)) as *mut _;
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
 
-        let mut ptr: *mut js_value_t = ptr::null_mut();
+        match mem::replace(&mut *state, PromiseFutureState::Done) {
+            PromiseFutureState::Pending(_) => {
+                *state = PromiseFutureState::Pending(Some(cx.waker().clone()));
 
-        let status = unsafe {
-            js_create_function(
-                env.ptr,
-                ptr::null_mut(),
-                0,
-                Some(Function::apply),
-                data,
-                &mut ptr,
-            )
-        };
+                task::Poll::Pending
+            }
+            PromiseFutureState::Settled(Ok(value)) => task::Poll::Ready(Ok(value)),
+            PromiseFutureState::Settled(Err(reason)) => {
+                let result = Env::from_raw(self.env).scope(|env| {
+                    let reason: Value = reason.get(env)?;
 
-        check_status!(env, status);
+                    Err(env.throw(reason))
+                });
 
-        unsafe {
-            js_add_finalizer(
-                env.ptr,
-                ptr,
-                data,
-                Some(Function::drop),
-                ptr::null_mut(),
-                ptr::null_mut(),
-            );
+                task::Poll::Ready(result)
+            }
+            PromiseFutureState::Done => panic!("`PromiseFuture` polled after completion"),
         }
-
-        Ok(Self(Value { env: env.ptr, ptr }))
     }
+}
+
+#[derive(Debug)]
+pub struct Deferred {
+    ptr: *mut js_deferred_t,
+}
 
-    pub fn call<'a, C, R, A>(&self, receiver: C, args: A) -> Result<R>
+impl Deferred {
+    pub fn resolve<'a, T>(self, env: &Env, value: T) -> Result<()>
     where
-        C: Into<Value>,
-        A: IntoIterator<Item = &'a Value>,
-        R: From<Value>,
+        T: Into<Value<'a>>,
     {
-        let env = Env::from(self.0.env);
-
-        let mut args: Vec<_> = args.into_iter().map(|value| value.ptr).collect();
+        let status = unsafe { js_resolve_deferred(env.ptr, self.ptr, value.into().ptr) };
 
-        let mut ptr: *mut js_value_t = ptr::null_mut();
-
-        let status = unsafe {
-            js_call_function(
-                env.ptr,
-                receiver.into().ptr,
-                self.0.ptr,
-                args.len(),
-                args.as_mut_ptr(),
-                &mut ptr,
-            )
-        };
+        check_status!(status);
 
-        check_status!(env, status);
-
-        Ok(Value { env: env.ptr, ptr }.into())
+        Ok(())
     }
 
-    extern "C" fn apply(env: *mut js_env_t, info: *mut js_callback_info_t) -> *mut js_value_t {
-        let mut len: usize = 0;
-        let mut receiver: *mut js_value_t = ptr::null_mut();
-        let mut data: *mut c_void = ptr::null_mut();
-
-        unsafe {
-            js_get_callback_info(
-                env,
-                info,
-                &mut len,
-                ptr::null_mut(),
-                &mut receiver,
-                &mut data,
-            );
-        }
-
-        let mut args = Vec::new();
-
-        if len > 0 {
-            args.resize(len, ptr::null_mut());
-
-            unsafe {
-                js_get_callback_info(
-                    env,
-                    info,
-                    &mut len,
-                    args.as_mut_ptr(),
-                    ptr::null_mut(),
-                    ptr::null_mut(),
-                );
-            }
-        }
+    pub fn reject<'a, T>(self, env: &Env, error: T) -> Result<()>
+    where
+        T: Into<Value<'a>>,
+    {
+        let status = unsafe { js_reject_deferred(env.ptr, self.ptr, error.into().ptr) };
 
-        let closure =
-            unsafe { &mut *(data as *mut Box<dyn FnMut(&Env, &Callback) -> *mut js_value_t>) };
+        check_status!(status);
 
-        closure(
-            &Env::from(env),
-            &Callback {
-                env,
-                args,
-                receiver,
-            },
-        )
+        Ok(())
     }
 
-    extern "C" fn drop(_: *mut js_env_t, data: *mut c_void, _: *mut c_void) -> () {
-        unsafe {
-            drop(Box::from_raw(data));
+    /// Resolves with the value or rejects with the exception of `result`.
+    pub fn settle(self, env: &Env, result: Result<Value<'_>>) -> Result<()> {
+        match result {
+            Ok(value) => self.resolve(env, value),
+            Err(err) => {
+                if env.is_exception_pending()? {
+                    self.reject(env, env.pending_exception()?)
+                } else {
+                    self.reject(env, Error::new(env, &err.to_string())?)
+                }
+            }
         }
     }
 }
 
-value_conversions!(Function);
-
 #[derive(Debug)]
-pub struct External(Value);
+pub struct External<'s>(Value<'s>);
 
-impl External {
-    pub fn new<T: 'static>(env: &Env, value: T) -> Result<Self> {
+impl<'s> External<'s> {
+    pub fn new<T: 'static>(env: &Env<'s>, value: T) -> Result<Self> {
         let data = Box::into_raw(Box::new(value)) as *mut _;
 
         let mut ptr: *mut js_value_t = ptr::null_mut();
@@ -1005,135 +3273,146 @@ impl External {
             js_create_external(
                 env.ptr,
                 data,
-                Some(External::drop),
+                Some(External::drop::<T>),
                 ptr::null_mut(),
                 &mut ptr,
             )
         };
 
-        check_status!(env, status);
-
-        Ok(Self(Value { env: env.ptr, ptr }))
-    }
+        check_status!(status);
 
-    extern "C" fn drop(_: *mut js_env_t, data: *mut c_void, _: *mut c_void) -> () {
-        unsafe {
-            drop(Box::from_raw(data));
-        }
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
     }
-}
 
-value_conversions!(External);
-
-impl<T> AsRef<T> for External {
-    fn as_ref(&self) -> &T {
+    pub fn as_ref<T>(&self) -> Result<&T> {
         let mut ptr: *mut c_void = ptr::null_mut();
 
-        unsafe {
-            js_get_value_external(self.0.env, self.0.ptr, &mut ptr);
-        }
+        let status = unsafe { js_get_value_external(self.0.env, self.0.ptr, &mut ptr) };
 
-        unsafe { &*(ptr as *const T) }
+        check_status!(status);
+
+        Ok(unsafe { &*(ptr as *const T) })
     }
-}
 
-impl<T> AsMut<T> for External {
-    fn as_mut(&mut self) -> &mut T {
+    pub fn as_mut<T>(&mut self) -> Result<&mut T> {
         let mut ptr: *mut c_void = ptr::null_mut();
 
+        let status = unsafe { js_get_value_external(self.0.env, self.0.ptr, &mut ptr) };
+
+        check_status!(status);
+
+        Ok(unsafe { &mut *(ptr as *mut T) })
+    }
+
+    extern "C" fn drop<T>(_: *mut js_env_t, data: *mut c_void, _: *mut c_void) {
         unsafe {
-            js_get_value_external(self.0.env, self.0.ptr, &mut ptr);
+            drop(Box::from_raw(data as *mut T));
         }
-
-        unsafe { &mut *(ptr as *mut T) }
     }
 }
 
+value_conversions!(External);
+
 #[derive(Debug)]
-pub struct ArrayBuffer(Value);
+pub struct ArrayBuffer<'s>(Value<'s>);
 
-impl ArrayBuffer {
-    pub fn new(env: &Env, len: usize) -> Result<Self> {
+impl<'s> ArrayBuffer<'s> {
+    pub fn new(env: &Env<'s>, len: usize) -> Result<Self> {
         let mut ptr: *mut js_value_t = ptr::null_mut();
 
         let status = unsafe { js_create_arraybuffer(env.ptr, len, ptr::null_mut(), &mut ptr) };
 
-        check_status!(env, status);
+        check_status!(status);
 
-        Ok(Self(Value { env: env.ptr, ptr }))
+        Ok(Self(Value::from_raw(env.ptr, ptr)))
     }
 
-    pub fn as_slice(&self) -> &[u8] {
-        let mut len: usize = 0;
-        let mut data: *mut c_void = ptr::null_mut();
-
-        unsafe {
-            js_get_arraybuffer_info(self.0.env, self.0.ptr, &mut data, &mut len);
-        }
+    pub fn as_slice(&self) -> Result<&[u8]> {
+        let (data, len) = self.info()?;
 
-        unsafe { slice::from_raw_parts(data as *const u8, len) }
+        Ok(unsafe { slice::from_raw_parts(data as *const u8, len) })
     }
 
-    pub fn as_mut_slice(&mut self) -> &mut [u8] {
-        let mut len: usize = 0;
-        let mut data: *mut c_void = ptr::null_mut();
+    pub fn as_mut_slice(&mut self) -> Result<&mut [u8]> {
+        let (data, len) = self.info()?;
 
-        unsafe {
-            js_get_arraybuffer_info(self.0.env, self.0.ptr, &mut data, &mut len);
-        }
+        Ok(unsafe { slice::from_raw_parts_mut(data as *mut u8, len) })
+    }
 
-        unsafe { slice::from_raw_parts_mut(data as *mut u8, len) }
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(self.as_slice()?.to_vec())
     }
 
-    pub fn to_vec(&self) -> Vec<u8> {
-        self.as_slice().to_vec()
+    pub fn copy_from_slice(&mut self, slice: &[u8]) -> Result<()> {
+        self.as_mut_slice()?.copy_from_slice(slice);
+
+        Ok(())
     }
 
-    pub fn copy_from_slice(&mut self, slice: &[u8]) {
-        self.as_mut_slice().copy_from_slice(slice)
+    pub fn clone_from_slice(&mut self, slice: &[u8]) -> Result<()> {
+        self.as_mut_slice()?.clone_from_slice(slice);
+
+        Ok(())
     }
 
-    pub fn clone_from_slice(&mut self, slice: &[u8]) {
-        self.as_mut_slice().clone_from_slice(slice)
+    fn info(&self) -> Result<(*mut c_void, usize)> {
+        let mut len: usize = 0;
+        let mut data: *mut c_void = ptr::null_mut();
+
+        let status =
+            unsafe { js_get_arraybuffer_info(self.0.env, self.0.ptr, &mut data, &mut len) };
+
+        check_status!(status);
+
+        // An empty buffer may have no backing store.
+        if data.is_null() {
+            data = ptr::NonNull::<u64>::dangling().as_ptr() as *mut c_void;
+        }
+
+        Ok((data, len))
     }
 }
 
 value_conversions!(ArrayBuffer);
 
 pub trait TypedArray<T> {
-    fn as_slice(&self) -> &[T];
+    fn as_slice(&self) -> Result<&[T]>;
 
-    fn as_mut_slice(&mut self) -> &mut [T];
+    fn as_mut_slice(&mut self) -> Result<&mut [T]>;
 
-    fn to_vec(&self) -> Vec<T>
+    fn to_vec(&self) -> Result<Vec<T>>
     where
         T: Clone,
     {
-        self.as_slice().to_vec()
+        Ok(self.as_slice()?.to_vec())
     }
 
-    fn copy_from_slice(&mut self, slice: &[T])
+    fn copy_from_slice(&mut self, slice: &[T]) -> Result<()>
     where
         T: Copy,
     {
-        self.as_mut_slice().copy_from_slice(slice)
+        self.as_mut_slice()?.copy_from_slice(slice);
+
+        Ok(())
     }
 
-    fn clone_from_slice(&mut self, slice: &[T])
+    fn clone_from_slice(&mut self, slice: &[T]) -> Result<()>
     where
         T: Clone,
     {
-        self.as_mut_slice().clone_from_slice(slice)
+        self.as_mut_slice()?.clone_from_slice(slice);
+
+        Ok(())
     }
 }
 
 macro_rules! define_typedarray {
     ($name:ident, $type:ident, $kind:ident) => {
         #[derive(Debug)]
-        pub struct $name(Value);
+        pub struct $name<'s>(Value<'s>);
 
-        impl $name {
-            pub fn new(env: &Env, len: usize) -> Result<Self> {
+        impl<'s> $name<'s> {
+            pub fn new(env: &Env<'s>, len: usize) -> Result<Self> {
                 let arraybuffer = ArrayBuffer::new(env, len * size_of::<$type>())?;
 
                 let mut ptr: *mut js_value_t = ptr::null_mut();
@@ -1149,26 +3428,26 @@ macro_rules! define_typedarray {
                     )
                 };
 
-                check_status!(env, status);
+                check_status!(status);
 
-                Ok(Self(Value { env: env.ptr, ptr }))
+                Ok(Self(Value::from_raw(env.ptr, ptr)))
             }
 
-            pub fn with_slice(env: &Env, slice: &[$type]) -> Result<Self> {
+            pub fn with_slice(env: &Env<'s>, slice: &[$type]) -> Result<Self> {
                 let mut typedarray = $name::new(env, slice.len())?;
 
-                typedarray.copy_from_slice(slice);
+                typedarray.copy_from_slice(slice)?;
 
                 Ok(typedarray)
             }
         }
 
-        impl TypedArray<$type> for $name {
-            fn as_slice(&self) -> &[$type] {
+        impl $name<'_> {
+            fn info(&self) -> Result<(*mut c_void, usize)> {
                 let mut len: usize = 0;
                 let mut data: *mut c_void = ptr::null_mut();
 
-                unsafe {
+                let status = unsafe {
                     js_get_typedarray_info(
                         self.0.env,
                         self.0.ptr,
@@ -1177,29 +3456,31 @@ macro_rules! define_typedarray {
                         &mut len,
                         ptr::null_mut(),
                         ptr::null_mut(),
-                    );
+                    )
+                };
+
+                check_status!(status);
+
+                // An empty array may have no backing store.
+                if data.is_null() {
+                    data = ptr::NonNull::<$type>::dangling().as_ptr() as *mut c_void;
                 }
 
-                unsafe { slice::from_raw_parts(data as *const $type, len) }
+                Ok((data, len))
             }
+        }
 
-            fn as_mut_slice(&mut self) -> &mut [$type] {
-                let mut len: usize = 0;
-                let mut data: *mut c_void = ptr::null_mut();
+        impl TypedArray<$type> for $name<'_> {
+            fn as_slice(&self) -> Result<&[$type]> {
+                let (data, len) = self.info()?;
 
-                unsafe {
-                    js_get_typedarray_info(
-                        self.0.env,
-                        self.0.ptr,
-                        ptr::null_mut(),
-                        &mut data,
-                        &mut len,
-                        ptr::null_mut(),
-                        ptr::null_mut(),
-                    );
-                }
+                Ok(unsafe { slice::from_raw_parts(data as *const $type, len) })
+            }
+
+            fn as_mut_slice(&mut self) -> Result<&mut [$type]> {
+                let (data, len) = self.info()?;
 
-                unsafe { slice::from_raw_parts_mut(data as *mut $type, len) }
+                Ok(unsafe { slice::from_raw_parts_mut(data as *mut $type, len) })
             }
         }
 
@@ -1222,19 +3503,20 @@ define_typedarray!(BigUint64Array, u32, js_biguint64array);
 macro_rules! define_error {
     ($name:ident, $create:ident) => {
         #[derive(Debug)]
-        pub struct $name(Value);
+        pub struct $name<'s>(Value<'s>);
 
-        impl $name {
-            pub fn new(env: &Env, message: &str) -> Self {
-                let message = String::new(env, message).unwrap();
+        impl<'s> $name<'s> {
+            pub fn new(env: &Env<'s>, message: &str) -> Result<Self> {
+                let message = String::new(env, message)?;
 
                 let mut ptr: *mut js_value_t = std::ptr::null_mut();
 
-                unsafe {
-                    $create(env.ptr, std::ptr::null_mut(), message.0.ptr, &mut ptr);
-                }
+                let status =
+                    unsafe { $create(env.ptr, std::ptr::null_mut(), message.0.ptr, &mut ptr) };
 
-                Self(Value { env: env.ptr, ptr })
+                check_status!(status);
+
+                Ok(Self(Value::from_raw(env.ptr, ptr)))
             }
         }
 
@@ -1256,18 +3538,7 @@ macro_rules! bare_exports {
             env: *mut $crate::ffi::js_env_t,
             _: *mut $crate::ffi::js_value_t,
         ) -> *mut $crate::ffi::js_value_t {
-            let result: Result<$crate::Value, $crate::Value> = $exports(crate::Env::from(env));
-
-            match result {
-                Ok(result) => result.into(),
-                Err(error) => {
-                    unsafe {
-                        $crate::ffi::js_throw(env, error.into());
-                    }
-
-                    std::ptr::null_mut()
-                }
-            }
+            unsafe { $crate::catch_panic(env, |env| $exports(env)) }
         }
     };
 }
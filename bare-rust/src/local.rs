@@ -0,0 +1,116 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use crate::ffi::*;
+use crate::{registry, uv, Env, Result};
+
+type Task = Pin<Box<dyn Future<Output = Result<()>>>>;
+
+// Runs futures on the JavaScript thread of an environment. Waking a task queues
+// it and signals the async handle, which polls the queued tasks on the next
+// turn of the loop.
+struct LocalExecutor {
+    signal: uv::Async,
+    queue: Arc<Mutex<Vec<u64>>>,
+    tasks: RefCell<HashMap<u64, Task>>,
+    next_id: Cell<u64>,
+}
+
+struct TaskWaker {
+    id: u64,
+    queue: Arc<Mutex<Vec<u64>>>,
+    signal: uv::AsyncSender,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.push(self.id);
+        }
+
+        self.signal.send();
+    }
+}
+
+pub(crate) fn spawn<F>(env: &Env, future: F) -> Result<()>
+where
+    F: Future<Output = Result<()>> + 'static,
+{
+    let executor = registry::state_or_insert_with(env, || {
+        Ok(LocalExecutor {
+            signal: registry::signal(env, drain)?,
+            queue: Arc::new(Mutex::new(Vec::new())),
+            tasks: RefCell::new(HashMap::new()),
+            next_id: Cell::new(0),
+        })
+    })?;
+
+    let id = executor.next_id.get();
+
+    executor.next_id.set(id + 1);
+
+    executor.tasks.borrow_mut().insert(id, Box::pin(future));
+
+    executor.signal.reference();
+
+    if let Ok(mut queue) = executor.queue.lock() {
+        queue.push(id);
+    }
+
+    executor.signal.send()?;
+
+    Ok(())
+}
+
+fn drain(env: *mut js_env_t) {
+    let Some(executor) = registry::state::<LocalExecutor>(&Env::from_raw(env)) else {
+        return;
+    };
+
+    let ids = match executor.queue.lock() {
+        Ok(mut queue) => std::mem::take(&mut *queue),
+        Err(_) => return,
+    };
+
+    for id in ids {
+        // The task is taken out of the executor while it is polled so that it
+        // may spawn further tasks.
+        let Some(mut task) = executor.tasks.borrow_mut().remove(&id) else {
+            continue;
+        };
+
+        let waker = Waker::from(Arc::new(TaskWaker {
+            id,
+            queue: executor.queue.clone(),
+            signal: executor.signal.sender(),
+        }));
+
+        let mut pending = false;
+
+        Env::run_detached(env, || {
+            match task.as_mut().poll(&mut Context::from_waker(&waker)) {
+                Poll::Ready(result) => result,
+                Poll::Pending => {
+                    pending = true;
+
+                    Ok(())
+                }
+            }
+        });
+
+        let mut tasks = executor.tasks.borrow_mut();
+
+        if pending {
+            tasks.insert(id, task);
+        } else if tasks.is_empty() {
+            executor.signal.unreference();
+        }
+    }
+}
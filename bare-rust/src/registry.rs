@@ -0,0 +1,163 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::ffi::*;
+use crate::{uv, Env, JsException, Name, Ref, Result};
+
+// Bookkeeping for an environment, created on first use and removed when the
+// environment is torn down.
+struct Entry {
+    alive: Arc<AtomicBool>,
+    state: HashMap<TypeId, Rc<dyn Any>>,
+    keys: HashMap<&'static str, Ref>,
+}
+
+thread_local! {
+    static ENVS: RefCell<HashMap<*mut js_env_t, Entry>> = RefCell::new(HashMap::new());
+
+    static ROOTS: RefCell<HashMap<*mut js_env_t, usize>> = RefCell::new(HashMap::new());
+}
+
+// Marks an `Env` minted from a raw pointer, such as the one passed to a
+// callback, as live until dropped. Values created through a live `Env` belong
+// to the innermost open handle scope, so no unrelated scope may be opened on
+// top of it while it is live.
+pub(crate) struct Root(*mut js_env_t);
+
+impl Root {
+    pub(crate) fn new(env: *mut js_env_t) -> Self {
+        ROOTS.with_borrow_mut(|roots| *roots.entry(env).or_default() += 1);
+
+        Self(env)
+    }
+}
+
+impl Drop for Root {
+    fn drop(&mut self) {
+        ROOTS.with_borrow_mut(|roots| {
+            if let Some(count) = roots.get_mut(&self.0) {
+                *count -= 1;
+
+                if *count == 0 {
+                    roots.remove(&self.0);
+                }
+            }
+        });
+    }
+}
+
+pub(crate) fn is_rooted(env: *mut js_env_t) -> bool {
+    ROOTS.with_borrow(|roots| roots.contains_key(&env))
+}
+
+fn with_entry<F, R>(env: &Env, f: F) -> Result<R>
+where
+    F: FnOnce(&mut Entry) -> R,
+{
+    let exists = ENVS.with_borrow(|envs| envs.contains_key(&env.ptr));
+
+    if !exists {
+        let status =
+            unsafe { js_add_teardown_callback(env.ptr, Some(teardown), env.ptr as *mut c_void) };
+
+        if status != 0 {
+            return Err(JsException::from(status));
+        }
+
+        let entry = Entry {
+            alive: Arc::new(AtomicBool::new(true)),
+            state: HashMap::new(),
+            keys: HashMap::new(),
+        };
+
+        ENVS.with_borrow_mut(|envs| envs.insert(env.ptr, entry));
+    }
+
+    ENVS.with_borrow_mut(|envs| match envs.get_mut(&env.ptr) {
+        Some(entry) => Ok(f(entry)),
+        None => Err(JsException::TornDown),
+    })
+}
+
+// Returns a flag that is cleared when the environment is torn down.
+pub(crate) fn liveness(env: &Env) -> Result<Arc<AtomicBool>> {
+    with_entry(env, |entry| entry.alive.clone())
+}
+
+pub(crate) fn state<T: 'static>(env: &Env) -> Option<Rc<T>> {
+    let state = ENVS.with_borrow(|envs| {
+        envs.get(&env.ptr)
+            .and_then(|entry| entry.state.get(&TypeId::of::<T>()).cloned())
+    });
+
+    state.and_then(|state| state.downcast().ok())
+}
+
+pub(crate) fn set_state<T: 'static>(env: &Env, state: T) -> Result<Option<Rc<T>>> {
+    let previous = with_entry(env, |entry| {
+        entry.state.insert(TypeId::of::<T>(), Rc::new(state))
+    })?;
+
+    Ok(previous.and_then(|state| state.downcast().ok()))
+}
+
+// Returns the state of type `T` for the environment, creating it with `init`
+// on first use.
+pub(crate) fn state_or_insert_with<T, F>(env: &Env, init: F) -> Result<Rc<T>>
+where
+    T: 'static,
+    F: FnOnce() -> Result<T>,
+{
+    if let Some(state) = state(env) {
+        return Ok(state);
+    }
+
+    let state = Rc::new(init()?);
+
+    with_entry(env, |entry| {
+        entry.state.insert(TypeId::of::<T>(), state.clone());
+    })?;
+
+    Ok(state)
+}
+
+// Creates an async handle that runs `drain` for the environment on the loop.
+// The handle starts out unreferenced so that it only keeps the loop alive while
+// its owner references it for pending work.
+pub(crate) fn signal(env: &Env, drain: fn(*mut js_env_t)) -> Result<uv::Async> {
+    let ptr = env.ptr;
+
+    let signal = uv::Async::new(&env.uv_loop()?, move || drain(ptr))?;
+
+    signal.unreference();
+
+    Ok(signal)
+}
+
+pub(crate) fn key<'s>(env: &Env<'s>, name: &str) -> Option<Result<Name<'s>>> {
+    ENVS.with_borrow(|envs| envs.get(&env.ptr)?.keys.get(name).map(|key| key.get(env)))
+}
+
+pub(crate) fn set_key(env: &Env, name: &'static str, key: Ref) -> Result<()> {
+    with_entry(env, |entry| {
+        entry.keys.insert(name, key);
+    })
+}
+
+extern "C" fn teardown(data: *mut c_void) {
+    let entry = ENVS.with_borrow_mut(|envs| envs.remove(&(data as *mut js_env_t)));
+
+    if let Some(entry) = entry {
+        // State is dropped while the environment is still alive so that any
+        // references it holds are released.
+        drop(entry.state);
+        drop(entry.keys);
+
+        entry.alive.store(false, Ordering::Release);
+    }
+}
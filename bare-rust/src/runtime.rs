@@ -0,0 +1,204 @@
+use std::alloc::{self, Layout};
+use std::ffi::{c_char, c_int, CString};
+use std::ptr;
+
+use crate::ffi::bare::*;
+use crate::ffi::uv::*;
+use crate::ffi::*;
+use crate::{c_string, Env, JsException, Result};
+
+/// An embedded Bare runtime with its own event loop. The application binary is
+/// responsible for linking against Bare and its JavaScript engine.
+#[derive(Debug)]
+pub struct Runtime {
+    uv_loop: *mut uv_loop_t,
+    platform: *mut js_platform_t,
+    bare: *mut bare_t,
+    env: *mut js_env_t,
+    args: Vec<CString>,
+    // Bare keeps the argument vector passed to `bare_setup()`.
+    argv: Vec<*const c_char>,
+}
+
+fn loop_layout() -> Layout {
+    Layout::from_size_align(unsafe { uv_loop_size() }, 16).unwrap()
+}
+
+impl Runtime {
+    /// Sets up a runtime with `args` as its process arguments, the first of which
+    /// is conventionally the name of the executable.
+    pub fn new<I, S>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args = args
+            .into_iter()
+            .map(|arg| c_string(arg.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let uv_loop = unsafe { alloc::alloc_zeroed(loop_layout()) as *mut uv_loop_t };
+
+        let status = unsafe { uv_loop_init(uv_loop) };
+
+        if status != 0 {
+            unsafe {
+                alloc::dealloc(uv_loop as *mut u8, loop_layout());
+            }
+        }
+
+        check_status!(status);
+
+        let mut runtime = Runtime {
+            uv_loop,
+            platform: ptr::null_mut(),
+            bare: ptr::null_mut(),
+            env: ptr::null_mut(),
+            args,
+            argv: Vec::new(),
+        };
+
+        let status = unsafe { js_create_platform(uv_loop, ptr::null(), &mut runtime.platform) };
+
+        check_status!(status);
+
+        runtime.argv = runtime.args.iter().map(|arg| arg.as_ptr()).collect();
+
+        let mut env: *mut js_env_t = ptr::null_mut();
+
+        let status = unsafe {
+            bare_setup(
+                uv_loop,
+                runtime.platform,
+                &mut env,
+                runtime.argv.len() as c_int,
+                runtime.argv.as_ptr(),
+                ptr::null(),
+                &mut runtime.bare,
+            )
+        };
+
+        check_status!(status);
+
+        runtime.env = env;
+
+        Ok(runtime)
+    }
+
+    /// Runs `f` in a handle scope of the environment of the runtime, which may
+    /// be used to install native modules before the entry script is loaded.
+    pub fn scope<F, R>(&mut self, f: F) -> Result<R>
+    where
+        F: for<'a> FnOnce(&mut Env<'a>) -> Result<R>,
+    {
+        Env::root_scope(self.env, f)
+    }
+
+    pub fn uv_loop(&self) -> crate::uv::Loop {
+        crate::uv::Loop::from_raw(self.uv_loop)
+    }
+
+    /// Loads the entry script. If `source` is `None` the script is read from
+    /// `filename`.
+    pub fn load(&mut self, filename: &str, source: Option<&[u8]>) -> Result<()> {
+        let filename = c_string(filename)?;
+
+        let source = source.map(|source| uv_buf_t {
+            base: source.as_ptr() as *mut c_char,
+            len: source.len() as _,
+        });
+
+        let status = unsafe {
+            bare_load(
+                self.bare,
+                filename.as_ptr(),
+                source.as_ref().map_or(ptr::null(), |source| source),
+                ptr::null_mut(),
+            )
+        };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    /// Runs the event loop until there is no more work to do.
+    pub fn run(&mut self) -> Result<()> {
+        let status = unsafe { bare_run(self.bare, uv_run_mode::UV_RUN_DEFAULT) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn exit(&mut self, exit_code: i32) -> Result<()> {
+        let status = unsafe { bare_exit(self.bare, exit_code) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn terminate(&mut self) -> Result<()> {
+        let status = unsafe { bare_terminate(self.bare) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    /// Tears down the runtime, returning the exit code set by the script.
+    pub fn teardown(mut self) -> Result<i32> {
+        self.close()
+    }
+
+    fn close(&mut self) -> Result<i32> {
+        let mut exit_code: c_int = 0;
+
+        // Bare and the platform are gone even if tearing them down fails, so
+        // they are forgotten before checking the status.
+        if !self.bare.is_null() {
+            let status =
+                unsafe { bare_teardown(self.bare, uv_run_mode::UV_RUN_DEFAULT, &mut exit_code) };
+
+            self.bare = ptr::null_mut();
+
+            check_status!(status);
+        }
+
+        if !self.platform.is_null() {
+            let status = unsafe { js_destroy_platform(self.platform) };
+
+            self.platform = ptr::null_mut();
+
+            check_status!(status);
+        }
+
+        if self.uv_loop.is_null() {
+            return Ok(exit_code);
+        }
+
+        // Run the loop once more to let handles closed during teardown finish.
+        unsafe {
+            uv_run(self.uv_loop, uv_run_mode::UV_RUN_DEFAULT);
+        }
+
+        let status = unsafe { uv_loop_close(self.uv_loop) };
+
+        check_status!(status);
+
+        unsafe {
+            alloc::dealloc(self.uv_loop as *mut u8, loop_layout());
+        }
+
+        self.uv_loop = ptr::null_mut();
+
+        Ok(exit_code)
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
@@ -0,0 +1,517 @@
+use std::alloc::{self, Layout};
+use std::error;
+use std::ffi::{c_int, c_void, CStr};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::ptr;
+use std::result;
+use std::sync::{Arc, Mutex};
+
+use crate::ffi::uv::*;
+use crate::{panic_policy, JsException, PanicPolicy};
+
+pub use crate::ffi::uv::{UV_DISCONNECT, UV_PRIORITIZED, UV_READABLE, UV_WRITABLE};
+
+macro_rules! check_status {
+    ($status:expr) => {
+        if $status < 0 {
+            return Err(Error($status));
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error(c_int);
+
+impl Error {
+    pub fn code(&self) -> c_int {
+        self.0
+    }
+
+    pub fn name(&self) -> &'static str {
+        unsafe { CStr::from_ptr(uv_err_name(self.0)) }
+            .to_str()
+            .unwrap_or("UNKNOWN")
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = unsafe { CStr::from_ptr(uv_strerror(self.0)) };
+
+        write!(f, "{}: {}", self.name(), message.to_string_lossy())
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<Error> for JsException {
+    fn from(error: Error) -> Self {
+        JsException::Status(error.0)
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub struct Loop {
+    pub(crate) ptr: *mut uv_loop_t,
+}
+
+impl Loop {
+    pub(crate) fn from_raw(ptr: *mut uv_loop_t) -> Self {
+        Self { ptr }
+    }
+
+    pub fn now(&self) -> u64 {
+        unsafe { uv_now(self.ptr) }
+    }
+
+    pub fn update_time(&self) {
+        unsafe { uv_update_time(self.ptr) }
+    }
+}
+
+fn handle_layout(kind: uv_handle_type) -> Layout {
+    Layout::from_size_align(unsafe { uv_handle_size(kind) }, 16).unwrap()
+}
+
+fn alloc_handle<T>(kind: uv_handle_type) -> *mut T {
+    unsafe { alloc::alloc_zeroed(handle_layout(kind)) as *mut T }
+}
+
+unsafe fn free_handle(handle: *mut uv_handle_t, kind: uv_handle_type) {
+    unsafe { alloc::dealloc(handle as *mut u8, handle_layout(kind)) }
+}
+
+// Every handle stores its callback slot, of type `C`, as the handle data. Both
+// the slot and the handle memory are released once libuv has finished closing
+// the handle, which may be after the owning wrapper has been dropped.
+fn close_handle<C>(handle: *mut uv_handle_t) {
+    unsafe { uv_close(handle, Some(on_close::<C>)) }
+}
+
+extern "C" fn on_close<C>(handle: *mut uv_handle_t) {
+    unsafe {
+        drop(Box::from_raw(uv_handle_get_data(handle) as *mut C));
+
+        free_handle(handle, uv_handle_get_type(handle));
+    }
+}
+
+fn handle_data<'a, C>(handle: *mut uv_handle_t) -> &'a mut C {
+    unsafe { &mut *(uv_handle_get_data(handle) as *mut C) }
+}
+
+// Runs a callback invoked by libuv, which must not be unwound into. There is no
+// JavaScript environment to throw a panic into, so under the throw policy the
+// panic is dropped once the panic hook has reported it.
+fn catch_panic<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce() -> R,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => Some(result),
+        Err(_) => {
+            if panic_policy() == PanicPolicy::Abort {
+                process::abort();
+            }
+
+            None
+        }
+    }
+}
+
+type AsyncCallback = Box<dyn FnMut()>;
+
+struct AsyncHandle(*mut uv_async_t);
+
+unsafe impl Send for AsyncHandle {}
+
+#[derive(Debug)]
+pub struct Async {
+    handle: *mut uv_async_t,
+    sender: Arc<Mutex<AsyncHandle>>,
+}
+
+impl Async {
+    pub fn new<F>(uv_loop: &Loop, callback: F) -> Result<Self>
+    where
+        F: FnMut() + 'static,
+    {
+        let handle = alloc_handle::<uv_async_t>(uv_handle_type::UV_ASYNC);
+
+        let status = unsafe { uv_async_init(uv_loop.ptr, handle, Some(Async::on_async)) };
+
+        if status < 0 {
+            unsafe {
+                free_handle(handle as *mut uv_handle_t, uv_handle_type::UV_ASYNC);
+            }
+        }
+
+        check_status!(status);
+
+        let callback: AsyncCallback = Box::new(callback);
+
+        unsafe {
+            uv_handle_set_data(
+                handle as *mut uv_handle_t,
+                Box::into_raw(Box::new(callback)) as *mut c_void,
+            );
+        }
+
+        Ok(Self {
+            handle,
+            sender: Arc::new(Mutex::new(AsyncHandle(handle))),
+        })
+    }
+
+    pub fn send(&self) -> Result<()> {
+        let status = unsafe { uv_async_send(self.handle) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn sender(&self) -> AsyncSender {
+        AsyncSender {
+            handle: self.sender.clone(),
+        }
+    }
+
+    pub fn reference(&self) {
+        unsafe { uv_ref(self.handle as *mut uv_handle_t) }
+    }
+
+    pub fn unreference(&self) {
+        unsafe { uv_unref(self.handle as *mut uv_handle_t) }
+    }
+
+    extern "C" fn on_async(handle: *mut uv_async_t) {
+        let callback = handle_data::<AsyncCallback>(handle as *mut uv_handle_t);
+
+        catch_panic(callback);
+    }
+}
+
+impl Drop for Async {
+    fn drop(&mut self) {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.0 = ptr::null_mut();
+        }
+
+        close_handle::<AsyncCallback>(self.handle as *mut uv_handle_t);
+    }
+}
+
+impl fmt::Debug for AsyncHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AsyncSender {
+    handle: Arc<Mutex<AsyncHandle>>,
+}
+
+impl AsyncSender {
+    /// Returns `false` if the handle has already been closed.
+    pub fn send(&self) -> bool {
+        match self.handle.lock() {
+            Ok(handle) if !handle.0.is_null() => unsafe { uv_async_send(handle.0) == 0 },
+            _ => false,
+        }
+    }
+}
+
+type TimerCallback = Option<Box<dyn FnMut()>>;
+
+#[derive(Debug)]
+pub struct Timer {
+    handle: *mut uv_timer_t,
+}
+
+impl Timer {
+    pub fn new(uv_loop: &Loop) -> Result<Self> {
+        let handle = alloc_handle::<uv_timer_t>(uv_handle_type::UV_TIMER);
+
+        let status = unsafe { uv_timer_init(uv_loop.ptr, handle) };
+
+        if status < 0 {
+            unsafe {
+                free_handle(handle as *mut uv_handle_t, uv_handle_type::UV_TIMER);
+            }
+        }
+
+        check_status!(status);
+
+        let callback: TimerCallback = None;
+
+        unsafe {
+            uv_handle_set_data(
+                handle as *mut uv_handle_t,
+                Box::into_raw(Box::new(callback)) as *mut c_void,
+            );
+        }
+
+        Ok(Self { handle })
+    }
+
+    pub fn start<F>(&mut self, timeout: u64, repeat: u64, callback: F) -> Result<()>
+    where
+        F: FnMut() + 'static,
+    {
+        *handle_data::<TimerCallback>(self.handle as *mut uv_handle_t) = Some(Box::new(callback));
+
+        let status =
+            unsafe { uv_timer_start(self.handle, Some(Timer::on_timeout), timeout, repeat) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        let status = unsafe { uv_timer_stop(self.handle) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn again(&mut self) -> Result<()> {
+        let status = unsafe { uv_timer_again(self.handle) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn repeat(&self) -> u64 {
+        unsafe { uv_timer_get_repeat(self.handle) }
+    }
+
+    pub fn set_repeat(&mut self, repeat: u64) {
+        unsafe { uv_timer_set_repeat(self.handle, repeat) }
+    }
+
+    pub fn reference(&self) {
+        unsafe { uv_ref(self.handle as *mut uv_handle_t) }
+    }
+
+    pub fn unreference(&self) {
+        unsafe { uv_unref(self.handle as *mut uv_handle_t) }
+    }
+
+    extern "C" fn on_timeout(handle: *mut uv_timer_t) {
+        let slot = handle_data::<TimerCallback>(handle as *mut uv_handle_t);
+
+        // The callback is taken out of its slot while it runs so that it may
+        // restart the timer with a new callback.
+        if let Some(mut callback) = slot.take() {
+            catch_panic(&mut callback);
+
+            if slot.is_none() {
+                *slot = Some(callback);
+            }
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        close_handle::<TimerCallback>(self.handle as *mut uv_handle_t);
+    }
+}
+
+type PollCallback = Option<Box<dyn FnMut(Result<c_int>)>>;
+
+#[derive(Debug)]
+pub struct Poll {
+    handle: *mut uv_poll_t,
+}
+
+impl Poll {
+    pub fn new(uv_loop: &Loop, fd: c_int) -> Result<Self> {
+        let handle = alloc_handle::<uv_poll_t>(uv_handle_type::UV_POLL);
+
+        let status = unsafe { uv_poll_init(uv_loop.ptr, handle, fd) };
+
+        Poll::init(handle, status)
+    }
+
+    pub fn with_socket(uv_loop: &Loop, socket: uv_os_sock_t) -> Result<Self> {
+        let handle = alloc_handle::<uv_poll_t>(uv_handle_type::UV_POLL);
+
+        let status = unsafe { uv_poll_init_socket(uv_loop.ptr, handle, socket) };
+
+        Poll::init(handle, status)
+    }
+
+    fn init(handle: *mut uv_poll_t, status: c_int) -> Result<Self> {
+        if status < 0 {
+            unsafe {
+                free_handle(handle as *mut uv_handle_t, uv_handle_type::UV_POLL);
+            }
+        }
+
+        check_status!(status);
+
+        let callback: PollCallback = None;
+
+        unsafe {
+            uv_handle_set_data(
+                handle as *mut uv_handle_t,
+                Box::into_raw(Box::new(callback)) as *mut c_void,
+            );
+        }
+
+        Ok(Self { handle })
+    }
+
+    pub fn start<F>(&mut self, events: c_int, callback: F) -> Result<()>
+    where
+        F: FnMut(Result<c_int>) + 'static,
+    {
+        *handle_data::<PollCallback>(self.handle as *mut uv_handle_t) = Some(Box::new(callback));
+
+        let status = unsafe { uv_poll_start(self.handle, events, Some(Poll::on_poll)) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        let status = unsafe { uv_poll_stop(self.handle) };
+
+        check_status!(status);
+
+        Ok(())
+    }
+
+    pub fn reference(&self) {
+        unsafe { uv_ref(self.handle as *mut uv_handle_t) }
+    }
+
+    pub fn unreference(&self) {
+        unsafe { uv_unref(self.handle as *mut uv_handle_t) }
+    }
+
+    extern "C" fn on_poll(handle: *mut uv_poll_t, status: c_int, events: c_int) {
+        let slot = handle_data::<PollCallback>(handle as *mut uv_handle_t);
+
+        if let Some(mut callback) = slot.take() {
+            let result = if status < 0 {
+                Err(Error(status))
+            } else {
+                Ok(events)
+            };
+
+            catch_panic(|| callback(result));
+
+            if slot.is_none() {
+                *slot = Some(callback);
+            }
+        }
+    }
+}
+
+impl Drop for Poll {
+    fn drop(&mut self) {
+        close_handle::<PollCallback>(self.handle as *mut uv_handle_t);
+    }
+}
+
+struct Work<W, R, A> {
+    work: Option<W>,
+    result: Option<R>,
+    after_work: Option<A>,
+}
+
+fn work_layout() -> Layout {
+    Layout::from_size_align(unsafe { uv_req_size(uv_req_type::UV_WORK) }, 16).unwrap()
+}
+
+/// Runs `work` on the libuv thread pool and then passes its result to
+/// `after_work` on the loop thread.
+pub fn queue_work<W, R, A>(uv_loop: &Loop, work: W, after_work: A) -> Result<()>
+where
+    W: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+    A: FnOnce(Result<R>) + 'static,
+{
+    let req = unsafe { alloc::alloc_zeroed(work_layout()) as *mut uv_work_t };
+
+    let data = Box::into_raw(Box::new(Work::<W, R, A> {
+        work: Some(work),
+        result: None,
+        after_work: Some(after_work),
+    }));
+
+    unsafe {
+        uv_req_set_data(req as *mut uv_req_t, data as *mut c_void);
+    }
+
+    let status = unsafe {
+        uv_queue_work(
+            uv_loop.ptr,
+            req,
+            Some(on_work::<W, R, A>),
+            Some(on_after_work::<W, R, A>),
+        )
+    };
+
+    if status < 0 {
+        unsafe {
+            drop(Box::from_raw(data));
+
+            alloc::dealloc(req as *mut u8, work_layout());
+        }
+    }
+
+    check_status!(status);
+
+    Ok(())
+}
+
+extern "C" fn on_work<W, R, A>(req: *mut uv_work_t)
+where
+    W: FnOnce() -> R,
+{
+    let data = unsafe { &mut *(uv_req_get_data(req as *mut uv_req_t) as *mut Work<W, R, A>) };
+
+    if let Some(work) = data.work.take() {
+        data.result = catch_panic(work);
+    }
+}
+
+extern "C" fn on_after_work<W, R, A>(req: *mut uv_work_t, status: c_int)
+where
+    A: FnOnce(Result<R>),
+{
+    let data =
+        unsafe { Box::from_raw(uv_req_get_data(req as *mut uv_req_t) as *mut Work<W, R, A>) };
+
+    unsafe {
+        alloc::dealloc(req as *mut u8, work_layout());
+    }
+
+    let Work {
+        result, after_work, ..
+    } = *data;
+
+    // Work that panicked leaves no result, so there is nothing to pass on.
+    let result = match result {
+        Some(result) if status == 0 => Ok(result),
+        None if status == 0 => return,
+        _ => Err(Error(status)),
+    };
+
+    if let Some(after_work) = after_work {
+        catch_panic(|| after_work(result));
+    }
+}